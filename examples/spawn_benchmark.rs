@@ -0,0 +1,80 @@
+//! Measures how long it takes to spawn a large blueprint, how many distinct
+//! (mesh, material) pairs its blocks use and the steady-state frame time
+//! afterwards, without opening a window or rendering.
+//!
+//! The renderer draws the blocks of each pair in one instanced batch, so the
+//! pair count bounds the block draw calls.
+//!
+//! ```sh
+//! cargo run --release --example spawn_benchmark -- <blocks>
+//! ```
+
+use std::{collections::HashSet, time::Instant};
+
+use archean_editor::{
+  BlockData, CommonAssets, PickingFilter,
+  blueprint::{Blueprint, FillPattern},
+  spawn_blueprint,
+};
+use bevy::{ecs::system::RunSystemOnce, prelude::*, state::app::StatesPlugin};
+use bevy_obj::ObjPlugin;
+
+/// The number of frames to average the frame time over.
+const FRAMES: u32 = 120;
+
+fn main() {
+  let count = std::env::args()
+    .nth(1)
+    .and_then(|arg| arg.parse::<u32>().ok())
+    .unwrap_or(100_000);
+  let side = (count as f64).cbrt().ceil() as u32;
+  let blueprint = Blueprint::synthetic(UVec3::splat(side), FillPattern::Solid);
+  let count = blueprint.data.blocks.len();
+
+  let mut app = App::new();
+  app
+    .add_plugins((MinimalPlugins, AssetPlugin::default(), StatesPlugin))
+    .init_asset::<Mesh>()
+    .init_asset::<StandardMaterial>()
+    .add_plugins(ObjPlugin)
+    .init_resource::<CommonAssets>();
+  app.finish();
+  app.cleanup();
+
+  let start = Instant::now();
+  app
+    .world_mut()
+    .run_system_once(
+      move |mut commands: Commands, common_assets: Res<CommonAssets>| {
+        spawn_blueprint(
+          &mut commands,
+          &common_assets,
+          &PickingFilter::default(),
+          &blueprint,
+        );
+      },
+    )
+    .expect("spawning the blueprint should not fail");
+  let spawn_time = start.elapsed();
+
+  // The first update does some one-off work, so leave it out of the average.
+  app.update();
+
+  let start = Instant::now();
+  for _ in 0..FRAMES {
+    app.update();
+  }
+  let frame_time = start.elapsed() / FRAMES;
+
+  let batches = app
+    .world_mut()
+    .query_filtered::<(&Mesh3d, &MeshMaterial3d<StandardMaterial>), With<BlockData>>()
+    .iter(app.world())
+    .map(|(mesh, material)| (mesh.id(), material.id()))
+    .collect::<HashSet<_>>()
+    .len();
+
+  println!("Spawned {count} blocks in {spawn_time:?}");
+  println!("Distinct (mesh, material) pairs: {batches}");
+  println!("Average frame time over {FRAMES} frames: {frame_time:?}");
+}
@@ -1,6 +1,25 @@
-use bevy::prelude::*;
+use core::{any::Any, time::Duration};
+use std::{
+  fs::File,
+  io::{BufReader, BufWriter, Write},
+  path::Path,
+};
 
-use crate::Selected;
+use bevy::{
+  ecs::entity::{EntityHashMap, EntityHashSet},
+  prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  BlockData, BlockEntities, BlockIndex, Locked, Selected,
+  blueprint::{Block, BlueprintState},
+  despawn_block, selection_bounds,
+  shape::GridAxis,
+  spawn_block_in_world,
+  symmetry::SymmetryPlane,
+  track_block_entity, untrack_block_entity,
+};
 
 #[derive(Default)]
 pub struct ActionPlugin;
@@ -9,61 +28,387 @@ impl Plugin for ActionPlugin {
   fn build(&self, app: &mut App) {
     app
       .add_message::<ActionMessage>()
+      .add_message::<HistoryChanged>()
       .init_resource::<ActionHistory>()
-      .add_systems(PostUpdate, consume_actions_messages);
+      .init_resource::<Respawned>()
+      .init_resource::<BlockEntities>()
+      .add_observer(track_block_entity)
+      .add_observer(untrack_block_entity)
+      .add_systems(PostUpdate, consume_actions_messages)
+      .add_systems(OnExit(BlueprintState::Loaded), clear_respawned);
   }
 }
 
-#[derive(Default, Resource)]
+#[derive(Resource)]
 pub struct ActionHistory {
   history: Vec<BoxedAction>,
   current: usize,
+  /// Whether actions that don't mutate blueprint data (such as selection
+  /// changes) are recorded. When disabled, they are applied directly and undo
+  /// and redo only step through real edits.
+  pub record_non_mutating: bool,
+  /// The most actions kept, beyond which the oldest are forgotten. Zero keeps
+  /// none, so nothing can be undone.
+  pub max_len: usize,
+  /// How soon after the last action a new one has to be pushed to be merged
+  /// into it, as described by [`Action::merge`].
+  pub merge_window: Duration,
+  /// When the last action was pushed, or [`None`] if the next one starts a
+  /// new entry regardless.
+  last_push: Option<Duration>,
+  /// The actions pushed since [`ActionMessage::BeginTransaction`], which are
+  /// applied but only recorded once the transaction ends. They are undone if
+  /// the history is cleared first, and discarded if the editor exits.
+  transaction: Option<Vec<BoxedAction>>,
+  dirty: bool,
+}
+
+impl Default for ActionHistory {
+  fn default() -> Self {
+    Self {
+      history: Vec::new(),
+      current: 0,
+      record_non_mutating: true,
+      max_len: 5000,
+      merge_window: Duration::from_millis(500),
+      last_push: None,
+      transaction: None,
+      dirty: false,
+    }
+  }
 }
 
 impl ActionHistory {
-  pub fn clear(&mut self) {
+  /// Returns an empty history keeping at most `max_len` actions.
+  ///
+  /// Insert it before adding [`ActionPlugin`] for it to be used instead of
+  /// the default.
+  pub fn with_max_len(max_len: usize) -> Self {
+    Self {
+      max_len,
+      ..Default::default()
+    }
+  }
+
+  /// Forgets every action, along with the blocks they have respawned, such as
+  /// when the blueprint is reloaded.
+  ///
+  /// The actions of an open transaction aren't recorded yet, so they are
+  /// undone rather than left applied without a way back.
+  pub fn clear(&mut self, world: &mut World) {
+    if let Some(actions) = self.transaction.take()
+      && let ActionResult::Failed(reason) = CombinedAction(actions).undo(world)
+    {
+      warn!("Could not undo the open transaction: {reason}");
+    }
+    if let Some(mut respawned) = world.get_resource_mut::<Respawned>() {
+      respawned.0.clear();
+    }
     self.history.clear();
     self.current = 0;
+    self.last_push = None;
+    self.transaction = None;
+    self.dirty = false;
+  }
+
+  /// How many actions are in the history, both applied and undone.
+  pub fn len(&self) -> usize {
+    self.history.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.history.is_empty()
+  }
+
+  /// How many actions are applied, which undo steps back from.
+  pub fn current_index(&self) -> usize {
+    self.current
+  }
+
+  /// Whether there is an applied action to undo.
+  pub fn can_undo(&self) -> bool {
+    self.current > 0
+  }
+
+  /// Whether there is an undone action to redo.
+  pub fn can_redo(&self) -> bool {
+    self.current < self.history.len()
+  }
+
+  /// Whether an action that mutates blueprint data has been applied, undone, or
+  /// redone since the history was cleared or last marked clean.
+  pub fn is_dirty(&self) -> bool {
+    self.dirty
+  }
+
+  /// Marks the blueprint data as unchanged, such as after saving it.
+  pub fn mark_clean(&mut self) {
+    self.dirty = false;
+  }
+
+  /// Iterates over the descriptions of the actions in the history, oldest
+  /// first, along with whether each one is currently applied. Actions that
+  /// aren't applied have been undone and can be redone.
+  pub fn descriptions(&self) -> impl Iterator<Item = (String, bool)> + '_ {
+    self
+      .history
+      .iter()
+      .enumerate()
+      .map(|(i, action)| (action.describe(), i < self.current))
+  }
+
+  /// The description of the action undo would revert, if there is one.
+  pub fn undo_label(&self) -> Option<String> {
+    self
+      .current
+      .checked_sub(1)
+      .and_then(|current| self.history.get(current))
+      .map(|action| action.describe())
+  }
+
+  /// The description of the action redo would apply again, if there is one.
+  pub fn redo_label(&self) -> Option<String> {
+    self
+      .history
+      .get(self.current)
+      .map(|action| action.describe())
+  }
+
+  /// Writes the history to `path` as JSON, referring to blocks by their
+  /// [`BlockIndex`], along with a fingerprint of the blocks as they are now.
+  ///
+  /// Actions that aren't [`SerializableAction`]s, or that refer to blocks
+  /// which can't be found, are written as stale entries.
+  pub fn save_to(
+    &self,
+    world: &mut World,
+    path: impl AsRef<Path>,
+  ) -> Result<(), HistoryError> {
+    // Deleted blocks aren't spawned, so their indices are only found in the
+    // actions that deleted them.
+    let mut deleted = EntityHashMap::default();
+    for action in self.history.iter() {
+      record_deleted_indices(&**action, &mut deleted);
+    }
+    let blocks = fingerprint_blocks(world);
+
+    let world = &*world;
+    let block_entities = world.resource::<BlockEntities>();
+    let index = |entity: Entity| {
+      let entity = resolve_entity(world, entity);
+      block_entities
+        .index(entity)
+        .or_else(|| deleted.get(&entity).copied())
+    };
+    let saved = SavedHistory {
+      blocks,
+      current: self.current,
+      actions: self
+        .history
+        .iter()
+        .map(|action| SerializableAction::from_action(&**action, &index))
+        .collect(),
+    };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, &saved)?;
+    writer.flush()?;
+    Ok(())
+  }
+
+  /// Replaces the history with the one written to `path` by
+  /// [`Self::save_to`], for the blocks as they are now.
+  ///
+  /// Block indices only identify the blocks they were saved with, and saving
+  /// the blueprint compacts them, so the history is rejected with
+  /// [`HistoryError::Stale`] unless the blocks are exactly as they were when
+  /// it was saved.
+  ///
+  /// Stale entries, and entries referring to blocks that no longer exist,
+  /// such as deleted ones, are dropped along with everything on the far side
+  /// of them from the current action, so that what is left can still be
+  /// undone and redone in order.
+  pub fn load_from(
+    &mut self,
+    world: &mut World,
+    path: impl AsRef<Path>,
+  ) -> Result<(), HistoryError> {
+    let saved = serde_json::from_reader::<_, SavedHistory>(BufReader::new(
+      File::open(path)?,
+    ))?;
+
+    if saved.blocks != fingerprint_blocks(world) {
+      return Err(HistoryError::Stale);
+    }
+
+    let block_entities = world.resource::<BlockEntities>();
+    let entity = |index: usize| block_entities.entity(index);
+    let actions = saved
+      .actions
+      .into_iter()
+      .map(|action| action.and_then(|action| action.into_action(&entity)))
+      .collect::<Vec<_>>();
+
+    let current = saved.current.min(actions.len());
+    let start = actions[..current]
+      .iter()
+      .rposition(Option::is_none)
+      .map_or(0, |position| position + 1);
+    let end = actions[current..]
+      .iter()
+      .position(Option::is_none)
+      .map_or(actions.len(), |position| current + position);
+    let dropped = actions.len() - (end - start);
+    if dropped > 0 {
+      warn!(
+        "Dropped {dropped} actions from the loaded history, as they are stale"
+      );
+    }
+
+    self.history = actions
+      .into_iter()
+      .take(end)
+      .skip(start)
+      .flatten()
+      .collect();
+    self.current = current - start;
+    let excess = self.history.len().saturating_sub(self.max_len);
+    self.history.drain(..excess);
+    self.current = self.current.saturating_sub(excess);
+    self.last_push = None;
+
+    world.write_message(HistoryChanged {
+      current: self.current,
+      len: self.history.len(),
+    });
+    Ok(())
+  }
+
+  /// Applies and records `action`, returning whether the history changed.
+  fn push(&mut self, mut action: BoxedAction, world: &mut World) -> bool {
+    if let ActionResult::Failed(reason) = action.redo(world) {
+      warn!("Could not push action: {reason}");
+      return false;
+    }
+    self.dirty |= action.mutates_data();
+
+    if let Some(transaction) = &mut self.transaction {
+      transaction.push(action);
+      return false;
+    }
+
+    self.record(action, world)
+  }
+
+  /// Records `action`, which has already been applied, returning whether the
+  /// history changed.
+  fn record(&mut self, action: BoxedAction, world: &World) -> bool {
+    if !action.mutates_data() && !self.record_non_mutating {
+      return false;
+    }
+
+    // Merge into the last action if it was pushed recently and nothing has
+    // been undone since.
+    let now = world.get_resource::<Time>().map(Time::elapsed);
+    let recent = self
+      .last_push
+      .zip(now)
+      .is_some_and(|(last, now)| now.saturating_sub(last) <= self.merge_window);
+    self.last_push = now;
+    if recent
+      && self.current == self.history.len()
+      && let Some(last) = self.history.last_mut()
+      && last.merge(&*action)
+    {
+      return true;
+    }
+
+    self.history.drain(self.current..);
+    self.history.push(action);
+
+    // Actions past the current one were just dropped, so only the oldest
+    // ones are left to forget.
+    let excess = self.history.len().saturating_sub(self.max_len);
+    self.history.drain(..excess);
+    self.current = self.history.len();
+    true
   }
 
-  fn push(&mut self, action: BoxedAction, world: &mut World) {
+  /// Starts collecting pushed actions into a single [`CombinedAction`].
+  fn begin_transaction(&mut self) {
+    if self.transaction.is_some() {
+      warn!("Could not begin transaction, as one is already open");
+      return;
+    }
+
+    self.transaction = Some(Vec::new());
+  }
+
+  /// Records the actions pushed since the transaction began as one, returning
+  /// whether the history changed.
+  fn end_transaction(&mut self, world: &World) -> bool {
+    let Some(actions) = self.transaction.take() else {
+      warn!("Could not end transaction, as none is open");
+      return false;
+    };
+
+    if actions.is_empty() {
+      return false;
+    }
+
+    self.record(Box::new(CombinedAction(actions)), world)
+  }
+
+  /// Redoes the next action, returning whether the history changed.
+  fn redo(&mut self, world: &mut World) -> bool {
+    if self.transaction.is_some() {
+      warn!("Could not redo action, as a transaction is open");
+      return false;
+    }
+
+    let Some(action) = self.history.get_mut(self.current) else {
+      return false;
+    };
+
     match action.redo(world) {
-      // TODO: Is there a way to make this nicely actionable?
-      ActionResult::Failed => {
-        warn!("Could not push action. There may be more information above")
+      ActionResult::Failed(reason) => {
+        warn!("Could not redo action: {reason}");
+        false
       }
       ActionResult::Success => {
-        self.history.drain(self.current..);
-        self.history.push(action);
-        self.current = self.history.len();
+        self.dirty |= action.mutates_data();
+        self.current += 1;
+        self.last_push = None;
+        true
       }
     }
   }
 
-  fn redo(&mut self, world: &mut World) {
-    if let Some(action) = self.history.get(self.current) {
-      match action.redo(world) {
-        // TODO: Is there a way to make this nicely actionable?
-        ActionResult::Failed => {
-          warn!("Could not redo action. There may be more information above")
-        }
-        ActionResult::Success => self.current += 1,
-      }
+  /// Undoes the current action, returning whether the history changed.
+  fn undo(&mut self, world: &mut World) -> bool {
+    if self.transaction.is_some() {
+      warn!("Could not undo action, as a transaction is open");
+      return false;
     }
-  }
 
-  fn undo(&mut self, world: &mut World) {
-    if let Some(action) = self
+    let Some(action) = self
       .current
       .checked_sub(1)
-      .and_then(|current| self.history.get(current))
-    {
-      match action.undo(world) {
-        // TODO: Is there a way to make this nicely actionable?
-        ActionResult::Failed => {
-          warn!("Could not undo action. There may be more information above")
-        }
-        ActionResult::Success => self.current -= 1,
+      .and_then(|current| self.history.get_mut(current))
+    else {
+      return false;
+    };
+
+    match action.undo(world) {
+      ActionResult::Failed(reason) => {
+        warn!("Could not undo action: {reason}");
+        false
+      }
+      ActionResult::Success => {
+        self.dirty |= action.mutates_data();
+        self.current -= 1;
+        self.last_push = None;
+        true
       }
     }
   }
@@ -78,37 +423,617 @@ pub enum ActionMessage {
   Redo,
   /// Undoes an action, if possible.
   Undo,
+  /// Ends merging into the last action, so the next one pushed starts a new
+  /// entry, such as at the end of a drag.
+  Commit,
+  /// Starts a transaction, collecting every action pushed until
+  /// [`Self::EndTransaction`] into a single entry in the history.
+  BeginTransaction,
+  /// Ends the open transaction, recording its actions as one
+  /// [`CombinedAction`].
+  EndTransaction,
+  /// Clears the history, as described by [`ActionHistory::clear`].
+  Clear,
 }
 
 pub type BoxedAction = Box<dyn Action>;
 
 /// Implemented by types that define an action that can be redone and undone.
-pub trait Action: Send + Sync {
+pub trait Action: Any + Send + Sync {
   /// Redoes the action.
-  fn redo(&self, world: &mut World) -> ActionResult;
+  fn redo(&mut self, world: &mut World) -> ActionResult;
   /// Undoes the action.
-  fn undo(&self, world: &mut World) -> ActionResult;
+  fn undo(&mut self, world: &mut World) -> ActionResult;
+  /// A short, human-readable description of the action.
+  fn describe(&self) -> String;
+  /// Whether the action changes blueprint data, as opposed to only editor
+  /// state such as the selection.
+  fn mutates_data(&self) -> bool {
+    true
+  }
+  /// Merges `other`, which has just been applied, into this action so that
+  /// both are undone and redone as one, returning whether it could.
+  ///
+  /// This is only tried for actions pushed in quick succession, to keep
+  /// something like a drag from filling the history.
+  fn merge(&mut self, _other: &dyn Action) -> bool {
+    false
+  }
 }
 
 /// The result of redoing or undoing an action.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ActionResult {
-  /// Failed to apply.
-  Failed,
+  /// Failed to apply, for the given reason.
+  Failed(String),
   /// Applied successfully.
   Success,
 }
 
+/// An error from saving or loading an [`ActionHistory`].
+#[derive(Debug)]
+pub enum HistoryError {
+  /// The history file could not be read or written.
+  Io(std::io::Error),
+  /// The history is not valid JSON, or doesn't match the format.
+  Parse(serde_json::Error),
+  /// The history was saved for different blocks than the ones spawned now.
+  Stale,
+}
+
+impl core::fmt::Display for HistoryError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::Io(error) => write!(f, "could not access history: {error}"),
+      Self::Parse(error) => write!(f, "could not parse history: {error}"),
+      Self::Stale => write!(f, "history was saved for different blocks"),
+    }
+  }
+}
+
+impl core::error::Error for HistoryError {
+  fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+    match self {
+      Self::Io(error) => Some(error),
+      Self::Parse(error) => Some(error),
+      Self::Stale => None,
+    }
+  }
+}
+
+impl From<std::io::Error> for HistoryError {
+  fn from(error: std::io::Error) -> Self {
+    Self::Io(error)
+  }
+}
+
+impl From<serde_json::Error> for HistoryError {
+  fn from(error: serde_json::Error) -> Self {
+    Self::Parse(error)
+  }
+}
+
+/// An [`ActionHistory`] as written by [`ActionHistory::save_to`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedHistory {
+  /// The [`fingerprint_blocks`] of the blocks the indices refer to.
+  blocks: u64,
+  current: usize,
+  /// The actions, or [`None`] for stale entries.
+  actions: Vec<Option<SerializableAction>>,
+}
+
+/// One of the crate's actions in a form that can be written to disk, referring
+/// to blocks by their [`BlockIndex`], as entities don't outlive the editor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SerializableAction {
+  Selection(usize),
+  Combined(Vec<SerializableAction>),
+  Delete(Vec<SerializableDeletedBlock>),
+  Place {
+    block: Block,
+    /// The index of the placed block, if it is placed.
+    placed: Option<usize>,
+  },
+  Flip {
+    blocks: Vec<usize>,
+    axis: GridAxis,
+  },
+  Mirror {
+    blocks: Vec<usize>,
+    axis: GridAxis,
+    twice_offset: i32,
+  },
+  Lock(Vec<usize>),
+  Translate {
+    blocks: Vec<usize>,
+    delta: [i32; 3],
+  },
+  Rotate {
+    blocks: Vec<usize>,
+    axis: GridAxis,
+    quarter_turns: i8,
+    pivot: Option<[i32; 3]>,
+  },
+  Insert {
+    kind: InsertKind,
+    blocks: Vec<Block>,
+    offset: [i32; 3],
+    /// The indices of the inserted blocks, which are empty unless they are
+    /// placed.
+    inserted: Vec<usize>,
+    deselected: Vec<usize>,
+  },
+  Paint {
+    blocks: Vec<usize>,
+    material: u8,
+    colors: FaceColorIndices,
+    /// The material and colors of each block before it was painted, which
+    /// are empty unless it is painted.
+    previous: Vec<(u8, FaceColorIndices)>,
+  },
+}
+
+/// A block removed by a [`DeleteBlockAction`], as written to disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerializableDeletedBlock {
+  pub index: usize,
+  pub block: Block,
+  pub selected: bool,
+}
+
+impl SerializableAction {
+  /// Converts `action`, looking up the index of each block it refers to with
+  /// `index`.
+  ///
+  /// Returns [`None`] if the action isn't one of the crate's, or refers to a
+  /// block without an index.
+  pub fn from_action(
+    action: &dyn Action,
+    index: &dyn Fn(Entity) -> Option<usize>,
+  ) -> Option<Self> {
+    let indices = |entities: &[Entity]| {
+      entities
+        .iter()
+        .map(|entity| index(*entity))
+        .collect::<Option<Vec<_>>>()
+    };
+
+    let action = action as &dyn Any;
+    if let Some(action) = action.downcast_ref::<SelectionAction>() {
+      Some(Self::Selection(index(action.0)?))
+    } else if let Some(action) = action.downcast_ref::<CombinedAction>() {
+      action
+        .iter()
+        .map(|action| Self::from_action(&**action, index))
+        .collect::<Option<_>>()
+        .map(Self::Combined)
+    } else if let Some(action) = action.downcast_ref::<DeleteBlockAction>() {
+      action
+        .blocks
+        .iter()
+        .map(|deleted| {
+          let (index, block) = deleted.block.clone()?;
+          Some(SerializableDeletedBlock {
+            index: index.0,
+            block,
+            selected: deleted.selected,
+          })
+        })
+        .collect::<Option<_>>()
+        .map(Self::Delete)
+    } else if let Some(action) = action.downcast_ref::<PlaceAction>() {
+      Some(Self::Place {
+        block: action.block.clone(),
+        placed: match action.entity {
+          Some(entity) => Some(index(entity)?),
+          None => None,
+        },
+      })
+    } else if let Some(action) = action.downcast_ref::<FlipAction>() {
+      Some(Self::Flip {
+        blocks: indices(&action.entities)?,
+        axis: action.axis,
+      })
+    } else if let Some(action) = action.downcast_ref::<MirrorAction>() {
+      Some(Self::Mirror {
+        blocks: indices(&action.entities)?,
+        axis: action.plane.axis,
+        twice_offset: action.plane.twice_offset,
+      })
+    } else if let Some(action) = action.downcast_ref::<LockAction>() {
+      Some(Self::Lock(indices(&action.0)?))
+    } else if let Some(action) = action.downcast_ref::<TranslateAction>() {
+      Some(Self::Translate {
+        blocks: indices(&action.entities)?,
+        delta: action.delta.to_array(),
+      })
+    } else if let Some(action) = action.downcast_ref::<RotateBlockAction>() {
+      Some(Self::Rotate {
+        blocks: indices(&action.entities)?,
+        axis: action.axis,
+        quarter_turns: action.quarter_turns,
+        pivot: action.pivot.map(|pivot| pivot.to_array()),
+      })
+    } else if let Some(action) = action.downcast_ref::<InsertBlocksAction>() {
+      Some(Self::Insert {
+        kind: action.kind,
+        blocks: action.blocks.clone(),
+        offset: action.offset.to_array(),
+        inserted: indices(&action.inserted)?,
+        deselected: indices(&action.deselected)?,
+      })
+    } else if let Some(action) = action.downcast_ref::<PaintMaterialAction>() {
+      Some(Self::Paint {
+        blocks: indices(&action.entities)?,
+        material: action.new_material,
+        colors: action.new_colors,
+        previous: action.previous.clone(),
+      })
+    } else {
+      None
+    }
+  }
+
+  /// Converts the action back, looking up the entity of each block it refers
+  /// to with `entity`.
+  ///
+  /// Returns [`None`] if it refers to a block without an entity.
+  pub fn into_action(
+    self,
+    entity: &dyn Fn(usize) -> Option<Entity>,
+  ) -> Option<BoxedAction> {
+    let entities = |indices: Vec<usize>| {
+      indices.into_iter().map(entity).collect::<Option<Vec<_>>>()
+    };
+
+    let action: BoxedAction = match self {
+      Self::Selection(index) => Box::new(SelectionAction(entity(index)?)),
+      Self::Combined(actions) => Box::new(CombinedAction(
+        actions
+          .into_iter()
+          .map(|action| action.into_action(entity))
+          .collect::<Option<_>>()?,
+      )),
+      Self::Delete(blocks) => Box::new(DeleteBlockAction {
+        blocks: blocks
+          .into_iter()
+          .map(|deleted| {
+            Some(DeletedBlock {
+              entity: entity(deleted.index)?,
+              block: Some((BlockIndex(deleted.index), deleted.block)),
+              selected: deleted.selected,
+            })
+          })
+          .collect::<Option<_>>()?,
+      }),
+      Self::Place { block, placed } => Box::new(PlaceAction {
+        block,
+        entity: match placed {
+          Some(index) => Some(entity(index)?),
+          None => None,
+        },
+        removed: None,
+      }),
+      Self::Flip { blocks, axis } => Box::new(FlipAction {
+        entities: entities(blocks)?,
+        axis,
+      }),
+      Self::Mirror {
+        blocks,
+        axis,
+        twice_offset,
+      } => Box::new(MirrorAction {
+        entities: entities(blocks)?,
+        plane: SymmetryPlane { axis, twice_offset },
+      }),
+      Self::Lock(blocks) => Box::new(LockAction(entities(blocks)?)),
+      Self::Translate { blocks, delta } => Box::new(TranslateAction {
+        entities: entities(blocks)?,
+        delta: IVec3::from_array(delta),
+      }),
+      Self::Rotate {
+        blocks,
+        axis,
+        quarter_turns,
+        pivot,
+      } => Box::new(RotateBlockAction {
+        entities: entities(blocks)?,
+        axis,
+        quarter_turns,
+        pivot: pivot.map(IVec3::from_array),
+      }),
+      Self::Insert {
+        kind,
+        blocks,
+        offset,
+        inserted,
+        deselected,
+      } => Box::new(InsertBlocksAction {
+        kind,
+        blocks,
+        offset: IVec3::from_array(offset),
+        inserted: entities(inserted)?,
+        removed: Vec::new(),
+        deselected: entities(deselected)?,
+      }),
+      Self::Paint {
+        blocks,
+        material,
+        colors,
+        previous,
+      } => Box::new(PaintMaterialAction {
+        entities: entities(blocks)?,
+        new_material: material,
+        new_colors: colors,
+        previous,
+      }),
+    };
+    Some(action)
+  }
+}
+
+/// Hashes the spawned blocks along with their indices, so that a saved history
+/// can tell whether its indices still refer to the same blocks.
+///
+/// This is FNV-1a over the blocks as JSON, in index order, which unlike
+/// [`std::hash::DefaultHasher`] is stable across builds.
+fn fingerprint_blocks(world: &mut World) -> u64 {
+  let mut blocks = world
+    .query::<(&BlockIndex, &BlockData)>()
+    .iter(world)
+    .collect::<Vec<_>>();
+  blocks.sort_by_key(|(index, _)| **index);
+
+  let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+  for (index, block) in blocks {
+    let bytes = serde_json::to_vec(&(index.0, &block.0)).unwrap_or_default();
+    for byte in bytes {
+      hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+  }
+  hash
+}
+
+/// Records the indices of the blocks removed by the [`DeleteBlockAction`]s in
+/// `action` in `indices`, under the entities they had when removed.
+fn record_deleted_indices(
+  action: &dyn Action,
+  indices: &mut EntityHashMap<usize>,
+) {
+  let action = action as &dyn Any;
+  if let Some(action) = action.downcast_ref::<CombinedAction>() {
+    for action in action.iter() {
+      record_deleted_indices(&**action, indices);
+    }
+  } else if let Some(action) = action.downcast_ref::<DeleteBlockAction>() {
+    for deleted in action.blocks.iter() {
+      if let Some((index, _)) = &deleted.block {
+        indices.entry(deleted.entity).or_insert(index.0);
+      }
+    }
+  }
+}
+
+/// Where blocks despawned and respawned by actions now live, since a respawned
+/// block gets a new entity.
+///
+/// Actions look the entities they hold up through this, so that they still
+/// find their blocks after an earlier action has respawned them.
+#[derive(Debug, Default, Resource)]
+pub struct Respawned(EntityHashMap<Entity>);
+
+impl Respawned {
+  /// Records that the block at `old` has been respawned as `new`.
+  pub fn record(&mut self, old: Entity, new: Entity) {
+    if old != new {
+      self.0.insert(old, new);
+    }
+  }
+
+  /// Returns the entity the block at `entity` lives at now.
+  pub fn resolve(&self, mut entity: Entity) -> Entity {
+    while let Some(next) = self.0.get(&entity) {
+      entity = *next;
+    }
+    entity
+  }
+}
+
+/// Forgets the blocks recorded in [`Respawned`], as they are despawned along
+/// with the blueprint.
+pub fn clear_respawned(mut respawned: ResMut<Respawned>) {
+  respawned.0.clear();
+}
+
+/// Returns the entity the block at `entity` lives at now, according to
+/// [`Respawned`].
+pub fn resolve_entity(world: &World, entity: Entity) -> Entity {
+  world
+    .get_resource::<Respawned>()
+    .map_or(entity, |respawned| respawned.resolve(entity))
+}
+
+/// Records that the block at `old` has been respawned as `new` in
+/// [`Respawned`].
+fn record_respawn(world: &mut World, old: Entity, new: Entity) {
+  if let Some(mut respawned) = world.get_resource_mut::<Respawned>() {
+    respawned.record(old, new);
+  }
+}
+
+/// Returns the index after every spawned block's, where new blocks go.
+fn next_block_index(world: &mut World) -> usize {
+  world
+    .query::<&BlockIndex>()
+    .iter(world)
+    .map(|index| index.0 + 1)
+    .max()
+    .unwrap_or(0)
+}
+
+/// Returns a failure saying the first of `entities` that is [`Locked`] can't
+/// be `verb`ed, if any is.
+fn find_locked<'a>(
+  world: &World,
+  entities: impl IntoIterator<Item = &'a Entity>,
+  verb: &str,
+) -> Option<ActionResult> {
+  entities
+    .into_iter()
+    .find(|entity| world.get::<Locked>(**entity).is_some())
+    .map(|entity| {
+      ActionResult::Failed(format!(
+        "could not {verb} entity {entity}, as it is locked"
+      ))
+    })
+}
+
+/// Moves each of `blocks` by `offset`, or returns a failure saying they can't
+/// be `verb`ed there, if one would be in a frame out of range or would overlap
+/// a block other than the blocks of `ignored`.
+fn offset_blocks<'a>(
+  world: &mut World,
+  blocks: &mut [Block],
+  offset: IVec3,
+  ignored: impl IntoIterator<Item = &'a Entity>,
+  verb: &str,
+) -> Option<ActionResult> {
+  for block in blocks.iter_mut() {
+    let min = block.min() + offset;
+    if !block.try_set_min(min) {
+      let IVec3 { x, y, z } = min;
+      return Some(ActionResult::Failed(format!(
+        "could not {verb} a block to ({x}, {y}, {z}), as it is out of range"
+      )));
+    }
+  }
+
+  find_overlap(world, blocks, ignored, verb)
+}
+
+/// Returns a failure saying `blocks` can't be `verb`ed where they are, if one
+/// overlaps a block other than the blocks of `ignored`.
+fn find_overlap<'a>(
+  world: &mut World,
+  blocks: &[Block],
+  ignored: impl IntoIterator<Item = &'a Entity>,
+  verb: &str,
+) -> Option<ActionResult> {
+  let ignored = ignored.into_iter().copied().collect::<EntityHashSet>();
+  world
+    .query::<(Entity, &BlockData)>()
+    .iter(world)
+    .filter(|(entity, _)| !ignored.contains(entity))
+    .find(|(_, other)| blocks.iter().any(|block| block.overlaps(other)))
+    .map(|(entity, _)| {
+      ActionResult::Failed(format!(
+        "could not {verb} blocks there, as they would overlap block {entity}"
+      ))
+    })
+}
+
+/// Mirrors the blocks of `entities` across the plane perpendicular to `axis`
+/// at half of `twice_plane`, as [`Block::mirror`] does.
+///
+/// Returns a failure saying they can't be `verb`ed, leaving every block as it
+/// is, if one of them isn't a block or is locked, or would end up in a frame
+/// out of range or overlapping another block.
+fn mirror_blocks(
+  world: &mut World,
+  entities: &mut [Entity],
+  axis: GridAxis,
+  twice_plane: i32,
+  verb: &str,
+) -> ActionResult {
+  let mut blocks = Vec::new();
+  for entity in entities.iter_mut() {
+    *entity = resolve_entity(world, *entity);
+    let Some(block) = world.get::<BlockData>(*entity) else {
+      return ActionResult::Failed(format!(
+        "could not {verb} entity {entity}, as it is not a block"
+      ));
+    };
+    blocks.push(block.0.clone());
+  }
+
+  if let Some(failed) = find_locked(world, entities.iter(), verb) {
+    return failed;
+  }
+
+  let mut unmirrored = Vec::new();
+  for block in blocks.iter_mut() {
+    match block.mirror(axis, twice_plane) {
+      Some(true) => {}
+      Some(false) => unmirrored.push(block.r#type),
+      None => {
+        return ActionResult::Failed(format!(
+          "could not {verb} a block, as it would be out of range"
+        ));
+      }
+    }
+  }
+  // The blocks are mirrored together, so they can only overlap the others.
+  if let Some(failed) = find_overlap(world, &blocks, entities.iter(), verb) {
+    return failed;
+  }
+
+  for type_id in unmirrored {
+    warn!(
+      "Block type {type_id} has no mirrored variant, so its orientation is kept"
+    );
+  }
+  for (entity, block) in entities.iter().zip(blocks) {
+    if let Some(mut block_data) = world.get_mut::<BlockData>(*entity) {
+      block_data.0 = block;
+    }
+  }
+
+  ActionResult::Success
+}
+
+/// A [`Message`] written whenever an action is pushed, undone or redone
+/// successfully, changing the [`ActionHistory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Message)]
+pub struct HistoryChanged {
+  /// How many actions are applied, which undo steps back from.
+  pub current: usize,
+  /// How many actions are in the history.
+  pub len: usize,
+}
+
 /// Consumes all of the [`ActionMessage`]s and applies them to the [`ActionHistory`].
 pub fn consume_actions_messages(world: &mut World) {
   world.resource_scope(|world, mut action_history: Mut<ActionHistory>| {
     world.resource_scope(
       |world, mut messages: Mut<Messages<ActionMessage>>| {
         for message in messages.drain() {
-          match message {
+          let changed = match message {
             ActionMessage::Push(action) => action_history.push(action, world),
             ActionMessage::Redo => action_history.redo(world),
             ActionMessage::Undo => action_history.undo(world),
+            ActionMessage::Commit => {
+              action_history.last_push = None;
+              false
+            }
+            ActionMessage::BeginTransaction => {
+              action_history.begin_transaction();
+              false
+            }
+            ActionMessage::EndTransaction => {
+              action_history.end_transaction(world)
+            }
+            ActionMessage::Clear => {
+              action_history.clear(world);
+              true
+            }
+          };
+
+          if changed {
+            world.write_message(HistoryChanged {
+              current: action_history.current,
+              len: action_history.history.len(),
+            });
           }
         }
       },
@@ -120,11 +1045,22 @@ pub fn consume_actions_messages(world: &mut World) {
 pub struct SelectionAction(pub Entity);
 
 impl Action for SelectionAction {
-  fn redo(&self, world: &mut World) -> ActionResult {
-    let mut entity = world.entity_mut(**self);
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    self.0 = resolve_entity(world, self.0);
+    let Ok(mut entity) = world.get_entity_mut(**self) else {
+      return ActionResult::Failed(format!(
+        "could not toggle selection of entity {}, as it no longer exists",
+        **self
+      ));
+    };
 
     if entity.contains::<Selected>() {
       entity.remove::<Selected>();
+    } else if entity.contains::<Locked>() {
+      return ActionResult::Failed(format!(
+        "could not select entity {}, as it is locked",
+        **self
+      ));
     } else {
       entity.insert(Selected);
     }
@@ -132,9 +1068,17 @@ impl Action for SelectionAction {
     ActionResult::Success
   }
 
-  fn undo(&self, world: &mut World) -> ActionResult {
+  fn undo(&mut self, world: &mut World) -> ActionResult {
     self.redo(world)
   }
+
+  fn describe(&self) -> String {
+    format!("Toggle selection of entity {}", self.0)
+  }
+
+  fn mutates_data(&self) -> bool {
+    false
+  }
 }
 
 #[derive(Deref, DerefMut)]
@@ -147,51 +1091,1088 @@ impl FromIterator<BoxedAction> for CombinedAction {
 }
 
 impl Action for CombinedAction {
-  fn redo(&self, world: &mut World) -> ActionResult {
-    for (i, action) in self.iter().enumerate() {
-      if action.redo(world) == ActionResult::Failed {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    for i in 0..self.len() {
+      if let ActionResult::Failed(reason) = self.0[i].redo(world) {
         warn!(
           "Could not redo all actions in combined action. Unwinding partial state"
         );
 
-        for action in self.iter().take(i.saturating_sub(1)).rev() {
-          if action.undo(world) == ActionResult::Failed {
-            warn!(
-              "Failed to unwind partial state. There may be more information above"
-            );
-            return ActionResult::Failed;
+        // Every action before the failed one was applied, so undo them.
+        for action in self.iter_mut().take(i).rev() {
+          if let ActionResult::Failed(unwind) = action.undo(world) {
+            return ActionResult::Failed(format!(
+              "{reason}, and failed to unwind partial state: {unwind}"
+            ));
           }
         }
 
         warn!("Successfully unwound partial state");
-        return ActionResult::Failed;
+        return ActionResult::Failed(reason);
       }
     }
 
     ActionResult::Success
   }
 
-  fn undo(&self, world: &mut World) -> ActionResult {
-    for (i, action) in self.iter().enumerate().rev() {
-      if action.undo(world) == ActionResult::Failed {
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    for i in (0..self.len()).rev() {
+      if let ActionResult::Failed(reason) = self.0[i].undo(world) {
         warn!(
           "Could not undo all actions in combined action. Unwinding partial state"
         );
 
-        for action in self.iter().rev().take(i.saturating_sub(1)).rev() {
-          if action.undo(world) == ActionResult::Failed {
-            warn!(
-              "Failed to unwind partial state. There may be more information above"
-            );
-            return ActionResult::Failed;
+        // Every action after the failed one was undone, so redo them.
+        for action in self.iter_mut().skip(i + 1) {
+          if let ActionResult::Failed(unwind) = action.redo(world) {
+            return ActionResult::Failed(format!(
+              "{reason}, and failed to unwind partial state: {unwind}"
+            ));
           }
         }
 
         warn!("Successfully unwound partial state");
-        return ActionResult::Failed;
+        return ActionResult::Failed(reason);
       }
     }
 
     ActionResult::Success
   }
+
+  fn describe(&self) -> String {
+    match self.as_slice() {
+      [action] => action.describe(),
+      actions => format!("{} combined actions", actions.len()),
+    }
+  }
+
+  fn mutates_data(&self) -> bool {
+    self.iter().any(|action| action.mutates_data())
+  }
+}
+
+/// Deletes blocks, respawning them when undone.
+///
+/// Respawned blocks get the same components and observers as when the
+/// blueprint was spawned, and are selected again if they were selected when
+/// deleted. Their transform and mesh follow from their [`BlockData`], so only
+/// that and their [`BlockIndex`] are kept.
+pub struct DeleteBlockAction {
+  blocks: Vec<DeletedBlock>,
+}
+
+struct DeletedBlock {
+  entity: Entity,
+  block: Option<(BlockIndex, Block)>,
+  selected: bool,
+}
+
+impl DeleteBlockAction {
+  pub fn new(entities: impl IntoIterator<Item = Entity>) -> Self {
+    Self {
+      blocks: entities
+        .into_iter()
+        .map(|entity| DeletedBlock {
+          entity,
+          block: None,
+          selected: false,
+        })
+        .collect(),
+    }
+  }
+}
+
+impl Action for DeleteBlockAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    for deleted in self.blocks.iter_mut() {
+      deleted.entity = resolve_entity(world, deleted.entity);
+    }
+
+    if let Some(failed) = find_locked(
+      world,
+      self.blocks.iter().map(|deleted| &deleted.entity),
+      "delete",
+    ) {
+      return failed;
+    }
+
+    // Check everything up front so that a bad entity doesn't leave the
+    // deletion half-applied.
+    if let Some(deleted) = self.blocks.iter().find(|deleted| {
+      world.get::<BlockData>(deleted.entity).is_none()
+        || world.get::<BlockIndex>(deleted.entity).is_none()
+    }) {
+      return ActionResult::Failed(format!(
+        "could not delete entity {}, as it is not a block",
+        deleted.entity
+      ));
+    }
+
+    for deleted in self.blocks.iter_mut() {
+      let entity = world.entity_mut(deleted.entity);
+      deleted.selected = entity.contains::<Selected>();
+      deleted.block = entity
+        .get::<BlockIndex>()
+        .copied()
+        .zip(entity.get::<BlockData>().map(|block| block.0.clone()));
+      despawn_block(entity);
+    }
+
+    ActionResult::Success
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    if self.blocks.iter().any(|deleted| deleted.block.is_none()) {
+      return ActionResult::Failed(
+        "could not restore deleted blocks, as they were never deleted".into(),
+      );
+    }
+
+    for deleted in self.blocks.iter_mut() {
+      let Some((index, block)) = deleted.block.clone() else {
+        continue;
+      };
+
+      // The respawned block gets a new entity, so keep track of it for the
+      // next redo, and for other actions referring to the block.
+      let entity = spawn_block_in_world(world, index, block);
+      record_respawn(world, deleted.entity, entity);
+      deleted.entity = entity;
+      if deleted.selected {
+        world.entity_mut(deleted.entity).insert(Selected);
+      }
+    }
+
+    ActionResult::Success
+  }
+
+  fn describe(&self) -> String {
+    format!("Delete {} blocks", self.blocks.len())
+  }
+}
+
+/// Places a block, despawning it when undone.
+pub struct PlaceAction {
+  block: Block,
+  entity: Option<Entity>,
+  /// The entity the block had before it was last removed, if it has been.
+  removed: Option<Entity>,
+}
+
+impl PlaceAction {
+  pub fn new(block: Block) -> Self {
+    Self {
+      block,
+      entity: None,
+      removed: None,
+    }
+  }
+}
+
+impl Action for PlaceAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    if self.entity.is_some() {
+      return ActionResult::Failed(
+        "could not place block, as it is already placed".into(),
+      );
+    }
+
+    let index = next_block_index(world);
+    let entity =
+      spawn_block_in_world(world, BlockIndex(index), self.block.clone());
+    if let Some(removed) = self.removed.take() {
+      record_respawn(world, removed, entity);
+    }
+    self.entity = Some(entity);
+
+    ActionResult::Success
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    let Some(entity) = self.entity.take() else {
+      return ActionResult::Failed(
+        "could not remove block, as it was never placed".into(),
+      );
+    };
+    let entity = resolve_entity(world, entity);
+
+    self.removed = Some(entity);
+    match world.get_entity_mut(entity) {
+      Ok(entity) => {
+        despawn_block(entity);
+        ActionResult::Success
+      }
+      Err(_) => ActionResult::Failed(format!(
+        "could not remove block {entity}, as it no longer exists"
+      )),
+    }
+  }
+
+  fn describe(&self) -> String {
+    format!("Place block of type {}", self.block.r#type)
+  }
+}
+
+/// Flips blocks across the plane through the middle of the box enclosing
+/// them, perpendicular to an axis, mirroring their shapes to match.
+///
+/// Flipping the same blocks again puts them back, so this is its own undo.
+pub struct FlipAction {
+  pub entities: Vec<Entity>,
+  pub axis: GridAxis,
+}
+
+impl FlipAction {
+  fn flip(&mut self, world: &mut World) -> ActionResult {
+    for entity in self.entities.iter_mut() {
+      *entity = resolve_entity(world, *entity);
+    }
+
+    let Some((min, max)) = selection_bounds(
+      self
+        .entities
+        .iter()
+        .filter_map(|entity| world.get::<BlockData>(*entity))
+        .map(|block| &block.0),
+    ) else {
+      return ActionResult::Success;
+    };
+
+    let twice_plane = ((min + max) * self.axis.unit()).element_sum();
+    mirror_blocks(world, &mut self.entities, self.axis, twice_plane, "flip")
+  }
+}
+
+impl Action for FlipAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    self.flip(world)
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    self.flip(world)
+  }
+
+  fn describe(&self) -> String {
+    format!("Flip {} blocks along {:?}", self.entities.len(), self.axis)
+  }
+}
+
+/// Mirrors blocks across a fixed plane, such as the blueprint's symmetry
+/// plane, mirroring their shapes to match.
+///
+/// Mirroring the same blocks again puts them back, so this is its own undo.
+pub struct MirrorAction {
+  pub entities: Vec<Entity>,
+  pub plane: SymmetryPlane,
+}
+
+impl MirrorAction {
+  fn mirror(&mut self, world: &mut World) -> ActionResult {
+    let SymmetryPlane { axis, twice_offset } = self.plane;
+    mirror_blocks(world, &mut self.entities, axis, twice_offset, "mirror")
+  }
+}
+
+impl Action for MirrorAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    self.mirror(world)
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    self.mirror(world)
+  }
+
+  fn describe(&self) -> String {
+    format!(
+      "Mirror {} blocks across {:?} = {}",
+      self.entities.len(),
+      self.plane.axis,
+      self.plane.twice_offset as f32 * 0.5
+    )
+  }
+}
+
+/// Toggles whether blocks are [`Locked`].
+///
+/// This doesn't deselect the blocks it locks, so combine it with
+/// [`SelectionAction`]s to do that.
+#[derive(Deref, DerefMut)]
+pub struct LockAction(pub Vec<Entity>);
+
+impl Action for LockAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    for entity in self.0.iter_mut() {
+      *entity = resolve_entity(world, *entity);
+    }
+
+    if let Some(entity) = self
+      .iter()
+      .find(|entity| world.get_entity(**entity).is_err())
+    {
+      return ActionResult::Failed(format!(
+        "could not toggle lock of entity {entity}, as it no longer exists"
+      ));
+    }
+
+    for entity in self.iter() {
+      let mut entity = world.entity_mut(*entity);
+      if entity.contains::<Locked>() {
+        entity.remove::<Locked>();
+      } else {
+        entity.insert(Locked);
+      }
+    }
+
+    ActionResult::Success
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    self.redo(world)
+  }
+
+  fn describe(&self) -> String {
+    format!("Toggle lock of {} blocks", self.len())
+  }
+
+  fn mutates_data(&self) -> bool {
+    false
+  }
+}
+
+/// Moves blocks by a whole number of cells.
+pub struct TranslateAction {
+  pub entities: Vec<Entity>,
+  /// How far to move the blocks, in blocks.
+  pub delta: IVec3,
+}
+
+impl TranslateAction {
+  fn translate(&mut self, world: &mut World, delta: IVec3) -> ActionResult {
+    for entity in self.entities.iter_mut() {
+      *entity = resolve_entity(world, *entity);
+    }
+
+    if let Some(entity) = self
+      .entities
+      .iter()
+      .find(|entity| world.get::<BlockData>(**entity).is_none())
+    {
+      return ActionResult::Failed(format!(
+        "could not move entity {entity}, as it is not a block"
+      ));
+    }
+
+    if let Some(failed) = find_locked(world, self.entities.iter(), "move") {
+      return failed;
+    }
+
+    let mut blocks = self
+      .entities
+      .iter()
+      .filter_map(|entity| Some(world.get::<BlockData>(*entity)?.0.clone()))
+      .collect::<Vec<_>>();
+    // The blocks move together, so they can only overlap the others.
+    if let Some(failed) =
+      offset_blocks(world, &mut blocks, delta, &self.entities, "move")
+    {
+      return failed;
+    }
+
+    for (entity, moved) in self.entities.iter().zip(blocks) {
+      if let Some(mut block) = world.get_mut::<BlockData>(*entity) {
+        block.0 = moved;
+      }
+    }
+
+    ActionResult::Success
+  }
+}
+
+impl Action for TranslateAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    self.translate(world, self.delta)
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    self.translate(world, -self.delta)
+  }
+
+  fn describe(&self) -> String {
+    let IVec3 { x, y, z } = self.delta;
+    format!("Move {} blocks by ({x}, {y}, {z})", self.entities.len())
+  }
+
+  fn merge(&mut self, other: &dyn Action) -> bool {
+    let Some(other) = (other as &dyn Any).downcast_ref::<Self>() else {
+      return false;
+    };
+
+    if other.entities != self.entities {
+      return false;
+    }
+
+    self.delta += other.delta;
+    true
+  }
+}
+
+/// Rotates blocks by quarter turns about an axis, around the middle of the box
+/// enclosing them, rotating their shapes and face colors to match.
+///
+/// Blocks are rotated on the grid rather than through their transforms, which
+/// follow from their [`BlockData`], so rotating back restores them exactly.
+pub struct RotateBlockAction {
+  pub entities: Vec<Entity>,
+  pub axis: GridAxis,
+  /// How many quarter turns to rotate by, counterclockwise looking from the
+  /// positive end of the axis, or clockwise if negative.
+  pub quarter_turns: i8,
+  /// The grid corner the blocks were first rotated around, which undoing and
+  /// redoing keep to.
+  pivot: Option<IVec3>,
+}
+
+impl RotateBlockAction {
+  pub fn new(entities: Vec<Entity>, axis: GridAxis, quarter_turns: i8) -> Self {
+    Self {
+      entities,
+      axis,
+      quarter_turns,
+      pivot: None,
+    }
+  }
+
+  fn rotate(&mut self, world: &mut World, turns: i32) -> ActionResult {
+    let mut blocks = Vec::new();
+    for entity in self.entities.iter_mut() {
+      *entity = resolve_entity(world, *entity);
+      let Some(block) = world.get::<BlockData>(*entity) else {
+        return ActionResult::Failed(format!(
+          "could not rotate entity {entity}, as it is not a block"
+        ));
+      };
+      blocks.push(block.0.clone());
+    }
+
+    if let Some(failed) = find_locked(world, self.entities.iter(), "rotate") {
+      return failed;
+    }
+
+    let Some((min, max)) = selection_bounds(blocks.iter()) else {
+      return ActionResult::Success;
+    };
+
+    let pivot = *self
+      .pivot
+      .get_or_insert_with(|| (min + max).div_euclid(IVec3::splat(2)));
+    let mut unrotated = Vec::new();
+    for block in blocks.iter_mut() {
+      match block.rotate(self.axis, turns, pivot) {
+        Some(true) => {}
+        Some(false) => unrotated.push(block.r#type),
+        None => {
+          return ActionResult::Failed(
+            "could not rotate a block, as it would be out of range".into(),
+          );
+        }
+      }
+    }
+    // The blocks turn together, so they can only overlap the others.
+    if let Some(failed) = find_overlap(world, &blocks, &self.entities, "rotate")
+    {
+      return failed;
+    }
+
+    for type_id in unrotated {
+      warn!(
+        "Block type {type_id} has no rotated variant, so its orientation is kept"
+      );
+    }
+    for (entity, block) in self.entities.iter().zip(blocks) {
+      if let Some(mut block_data) = world.get_mut::<BlockData>(*entity) {
+        block_data.0 = block;
+      }
+    }
+
+    ActionResult::Success
+  }
+}
+
+impl Action for RotateBlockAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    self.rotate(world, self.quarter_turns.into())
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    self.rotate(world, -i32::from(self.quarter_turns))
+  }
+
+  fn describe(&self) -> String {
+    format!(
+      "Rotate {} blocks {} quarter turns about {:?}",
+      self.entities.len(),
+      self.quarter_turns,
+      self.axis
+    )
+  }
+}
+
+/// How an [`InsertBlocksAction`] came about, which decides how it is
+/// described.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InsertKind {
+  /// Copies of blocks, moved by a whole number of cells.
+  Clone,
+  /// Blocks from a clipboard, moved to where they are pasted.
+  Paste,
+}
+
+impl InsertKind {
+  fn verb(self) -> &'static str {
+    match self {
+      Self::Clone => "clone",
+      Self::Paste => "paste",
+    }
+  }
+}
+
+/// Places blocks moved by an offset, which become the selection, despawning
+/// them and selecting the old selection again when undone.
+///
+/// This both clones and pastes blocks, as built by [`Self::clone_blocks`] and
+/// [`Self::paste`].
+pub struct InsertBlocksAction {
+  pub kind: InsertKind,
+  /// The blocks to place, before they are moved.
+  pub blocks: Vec<Block>,
+  /// How far the blocks are moved, in blocks.
+  pub offset: IVec3,
+  /// The placed blocks, while they are placed.
+  inserted: Vec<Entity>,
+  /// The entities the placed blocks had before they were last removed, if
+  /// they have been.
+  removed: Vec<Entity>,
+  /// The blocks that were selected before placing.
+  deselected: Vec<Entity>,
+}
+
+impl InsertBlocksAction {
+  fn new(kind: InsertKind, blocks: Vec<Block>, offset: IVec3) -> Self {
+    Self {
+      kind,
+      blocks,
+      offset,
+      inserted: Vec::new(),
+      removed: Vec::new(),
+      deselected: Vec::new(),
+    }
+  }
+
+  /// Places copies of `blocks` moved by `offset`.
+  pub fn clone_blocks(blocks: Vec<Block>, offset: IVec3) -> Self {
+    Self::new(InsertKind::Clone, blocks, offset)
+  }
+
+  /// Places `blocks`, given relative to `origin`, such as from a clipboard.
+  pub fn paste(blocks: Vec<Block>, origin: IVec3) -> Self {
+    Self::new(InsertKind::Paste, blocks, origin)
+  }
+}
+
+impl Action for InsertBlocksAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    let verb = self.kind.verb();
+    if !self.inserted.is_empty() {
+      return ActionResult::Failed(format!(
+        "could not {verb} blocks, as they are already placed"
+      ));
+    }
+
+    let mut blocks = self.blocks.clone();
+    if let Some(failed) =
+      offset_blocks(world, &mut blocks, self.offset, [], verb)
+    {
+      return failed;
+    }
+
+    self.deselected = world
+      .query_filtered::<Entity, With<Selected>>()
+      .iter(world)
+      .collect();
+    for entity in self.deselected.iter() {
+      world.entity_mut(*entity).remove::<Selected>();
+    }
+
+    // The blocks go after every existing block, in order.
+    let first_index = next_block_index(world);
+    let removed = core::mem::take(&mut self.removed);
+    for (i, block) in blocks.into_iter().enumerate() {
+      let entity =
+        spawn_block_in_world(world, BlockIndex(first_index + i), block);
+      world.entity_mut(entity).insert(Selected);
+      if let Some(removed) = removed.get(i) {
+        record_respawn(world, *removed, entity);
+      }
+      self.inserted.push(entity);
+    }
+
+    ActionResult::Success
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    let verb = self.kind.verb();
+    if self.inserted.is_empty() {
+      return ActionResult::Failed(format!(
+        "could not remove {verb}d blocks, as they were never placed"
+      ));
+    }
+
+    for entity in self.inserted.iter_mut() {
+      *entity = resolve_entity(world, *entity);
+    }
+
+    if let Some(entity) = self
+      .inserted
+      .iter()
+      .find(|entity| world.get_entity(**entity).is_err())
+    {
+      return ActionResult::Failed(format!(
+        "could not remove {verb}d block {entity}, as it no longer exists"
+      ));
+    }
+
+    for entity in self.inserted.iter() {
+      despawn_block(world.entity_mut(*entity));
+    }
+    self.removed = core::mem::take(&mut self.inserted);
+
+    for entity in self.deselected.iter_mut() {
+      *entity = resolve_entity(world, *entity);
+      if let Ok(mut entity) = world.get_entity_mut(*entity) {
+        entity.insert(Selected);
+      }
+    }
+
+    ActionResult::Success
+  }
+
+  fn describe(&self) -> String {
+    let IVec3 { x, y, z } = self.offset;
+    let len = self.blocks.len();
+    match self.kind {
+      InsertKind::Clone => format!("Clone {len} blocks by ({x}, {y}, {z})"),
+      InsertKind::Paste => format!("Paste {len} blocks at ({x}, {y}, {z})"),
+    }
+  }
+}
+
+/// The palette index of each face color of a block, as in [`Block::colors`].
+pub type FaceColorIndices = (u8, u8, u8, u8, u8, u8, u8);
+
+/// Repaints blocks with a material and face colors, restoring each block's own
+/// when undone.
+///
+/// The blocks' materials follow from their [`BlockData`], so colors missing
+/// from the palette show as blank.
+pub struct PaintMaterialAction {
+  pub entities: Vec<Entity>,
+  pub new_material: u8,
+  pub new_colors: FaceColorIndices,
+  /// The material and colors of each block before it was painted, while it
+  /// is painted.
+  previous: Vec<(u8, FaceColorIndices)>,
+}
+
+impl PaintMaterialAction {
+  pub fn new(
+    entities: Vec<Entity>,
+    new_material: u8,
+    new_colors: FaceColorIndices,
+  ) -> Self {
+    Self {
+      entities,
+      new_material,
+      new_colors,
+      previous: Vec::new(),
+    }
+  }
+}
+
+impl Action for PaintMaterialAction {
+  fn redo(&mut self, world: &mut World) -> ActionResult {
+    if !self.previous.is_empty() {
+      return ActionResult::Failed(
+        "could not paint blocks, as they are already painted".into(),
+      );
+    }
+
+    for entity in self.entities.iter_mut() {
+      *entity = resolve_entity(world, *entity);
+    }
+
+    if let Some(entity) = self
+      .entities
+      .iter()
+      .find(|entity| world.get::<BlockData>(**entity).is_none())
+    {
+      return ActionResult::Failed(format!(
+        "could not paint entity {entity}, as it is not a block"
+      ));
+    }
+
+    if let Some(failed) = find_locked(world, self.entities.iter(), "paint") {
+      return failed;
+    }
+
+    for entity in self.entities.iter() {
+      if let Some(mut block) = world.get_mut::<BlockData>(*entity) {
+        self.previous.push((block.material, block.colors));
+        block.material = self.new_material;
+        block.colors = self.new_colors;
+      }
+    }
+
+    ActionResult::Success
+  }
+
+  fn undo(&mut self, world: &mut World) -> ActionResult {
+    if self.previous.len() != self.entities.len() {
+      return ActionResult::Failed(
+        "could not restore painted blocks, as they were never painted".into(),
+      );
+    }
+
+    for entity in self.entities.iter_mut() {
+      *entity = resolve_entity(world, *entity);
+    }
+
+    if let Some(entity) = self
+      .entities
+      .iter()
+      .find(|entity| world.get::<BlockData>(**entity).is_none())
+    {
+      return ActionResult::Failed(format!(
+        "could not restore entity {entity}, as it is not a block"
+      ));
+    }
+
+    for (entity, (material, colors)) in
+      self.entities.iter().zip(self.previous.drain(..))
+    {
+      if let Some(mut block) = world.get_mut::<BlockData>(*entity) {
+        block.material = material;
+        block.colors = colors;
+      }
+    }
+
+    ActionResult::Success
+  }
+
+  fn describe(&self) -> String {
+    format!(
+      "Paint {} blocks with material {} and color {}",
+      self.entities.len(),
+      self.new_material,
+      self.new_colors.0
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    FRAME_SIZE,
+    blueprint::{Blueprint, FillPattern},
+  };
+
+  /// Spawns the blocks of a row of `len` unit blocks along X, tracked in
+  /// [`BlockEntities`], returning their entities in index order.
+  fn spawn_row(world: &mut World, len: u32) -> Vec<Entity> {
+    world.init_resource::<BlockEntities>();
+    world.add_observer(track_block_entity);
+    world.add_observer(untrack_block_entity);
+    let blueprint =
+      Blueprint::synthetic(UVec3::new(len, 1, 1), FillPattern::Solid);
+    blueprint
+      .data
+      .blocks
+      .into_iter()
+      .enumerate()
+      .map(|(i, block)| world.spawn((BlockIndex(i), BlockData(block))).id())
+      .collect()
+  }
+
+  fn history_file(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!(
+      "archean-editor-{name}-{}.history.json",
+      std::process::id()
+    ))
+  }
+
+  #[test]
+  fn saved_history_undoes_after_loading() {
+    let path = history_file("load");
+    let mut world = World::new();
+    let blocks = spawn_row(&mut world, 2);
+    let mut history = ActionHistory::default();
+    history.push(
+      Box::new(TranslateAction {
+        entities: vec![blocks[1]],
+        delta: IVec3::Y,
+      }),
+      &mut world,
+    );
+    history.save_to(&mut world, &path).unwrap();
+
+    let mut loaded = ActionHistory::default();
+    loaded.load_from(&mut world, &path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((loaded.len(), loaded.current_index()), (1, 1));
+
+    assert!(loaded.undo(&mut world));
+    let block = world.get::<BlockData>(blocks[1]).unwrap();
+    assert_eq!(block.min(), IVec3::X);
+  }
+
+  #[test]
+  fn saved_history_is_rejected_for_changed_blocks() {
+    let path = history_file("stale");
+    let mut world = World::new();
+    let blocks = spawn_row(&mut world, 2);
+    let mut history = ActionHistory::default();
+    history.push(Box::new(SelectionAction(blocks[0])), &mut world);
+    history.save_to(&mut world, &path).unwrap();
+
+    // Changing a block outside the history, such as by compacting the
+    // indices when the blueprint is saved, invalidates it.
+    world.get_mut::<BlockData>(blocks[1]).unwrap().r#type = 1;
+    let result = ActionHistory::default().load_from(&mut world, &path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(HistoryError::Stale)));
+  }
+
+  #[derive(Resource, Default)]
+  struct Total(i32);
+
+  /// Adds `amount` to [`Total`], failing instead when set to.
+  struct Add {
+    amount: i32,
+    fail_redo: bool,
+    fail_undo: bool,
+  }
+
+  impl Add {
+    fn ok(amount: i32) -> BoxedAction {
+      Box::new(Self {
+        amount,
+        fail_redo: false,
+        fail_undo: false,
+      })
+    }
+  }
+
+  impl Action for Add {
+    fn redo(&mut self, world: &mut World) -> ActionResult {
+      if self.fail_redo {
+        return ActionResult::Failed("redo failed".into());
+      }
+      world.resource_mut::<Total>().0 += self.amount;
+      ActionResult::Success
+    }
+
+    fn undo(&mut self, world: &mut World) -> ActionResult {
+      if self.fail_undo {
+        return ActionResult::Failed("undo failed".into());
+      }
+      world.resource_mut::<Total>().0 -= self.amount;
+      ActionResult::Success
+    }
+
+    fn describe(&self) -> String {
+      format!("Add {}", self.amount)
+    }
+  }
+
+  #[test]
+  fn failed_combined_redo_undoes_earlier_actions() {
+    let mut world = World::new();
+    world.init_resource::<Total>();
+    let mut action = CombinedAction(vec![
+      Add::ok(1),
+      Add::ok(10),
+      Box::new(Add {
+        amount: 100,
+        fail_redo: true,
+        fail_undo: false,
+      }),
+      Add::ok(1000),
+    ]);
+
+    let result = action.redo(&mut world);
+    assert_eq!(result, ActionResult::Failed("redo failed".into()));
+    assert_eq!(world.resource::<Total>().0, 0);
+  }
+
+  #[test]
+  fn failed_combined_undo_redoes_later_actions() {
+    let mut world = World::new();
+    world.init_resource::<Total>();
+    let mut action = CombinedAction(vec![
+      Add::ok(1),
+      Box::new(Add {
+        amount: 10,
+        fail_redo: false,
+        fail_undo: true,
+      }),
+      Add::ok(100),
+      Add::ok(1000),
+    ]);
+    assert_eq!(action.redo(&mut world), ActionResult::Success);
+    assert_eq!(world.resource::<Total>().0, 1111);
+
+    let result = action.undo(&mut world);
+    assert_eq!(result, ActionResult::Failed("undo failed".into()));
+    assert_eq!(world.resource::<Total>().0, 1111);
+  }
+
+  #[test]
+  fn transactions_mark_dirty_and_are_undone_on_clear() {
+    let mut world = World::new();
+    world.init_resource::<Total>();
+    let mut history = ActionHistory::default();
+    history.begin_transaction();
+    history.push(Add::ok(1), &mut world);
+    history.push(Add::ok(2), &mut world);
+    assert!(history.is_dirty());
+
+    history.clear(&mut world);
+    assert_eq!(world.resource::<Total>().0, 0);
+    assert!(!history.is_dirty());
+    assert!(history.is_empty());
+  }
+
+  #[test]
+  fn clear_forgets_respawned_blocks() {
+    let mut world = World::new();
+    world.init_resource::<Respawned>();
+    let blocks = spawn_row(&mut world, 2);
+    world
+      .resource_mut::<Respawned>()
+      .record(blocks[0], blocks[1]);
+    assert_eq!(resolve_entity(&world, blocks[0]), blocks[1]);
+
+    ActionHistory::default().clear(&mut world);
+    assert_eq!(resolve_entity(&world, blocks[0]), blocks[0]);
+  }
+
+  #[test]
+  fn translate_fails_onto_other_blocks() {
+    let mut world = World::new();
+    let blocks = spawn_row(&mut world, 3);
+    let mut action = TranslateAction {
+      entities: vec![blocks[0]],
+      delta: IVec3::X,
+    };
+    assert!(matches!(action.redo(&mut world), ActionResult::Failed(_)));
+    let block = world.get::<BlockData>(blocks[0]).unwrap();
+    assert_eq!(block.min(), IVec3::ZERO);
+
+    // Moving the whole row only lands on cells it leaves.
+    let mut action = TranslateAction {
+      entities: blocks.clone(),
+      delta: IVec3::X,
+    };
+    assert!(matches!(action.redo(&mut world), ActionResult::Success));
+  }
+
+  #[test]
+  fn mirror_fails_onto_other_blocks() {
+    let mut world = World::new();
+    let blocks = spawn_row(&mut world, 3);
+    let mut action = MirrorAction {
+      entities: vec![blocks[0]],
+      plane: SymmetryPlane {
+        axis: GridAxis::X,
+        twice_offset: 3,
+      },
+    };
+    assert!(matches!(action.redo(&mut world), ActionResult::Failed(_)));
+    let block = world.get::<BlockData>(blocks[0]).unwrap();
+    assert_eq!(block.min(), IVec3::ZERO);
+
+    // Mirroring the whole row lands each block where another one leaves.
+    let mut action = MirrorAction {
+      entities: blocks.clone(),
+      plane: SymmetryPlane {
+        axis: GridAxis::X,
+        twice_offset: 3,
+      },
+    };
+    assert!(matches!(action.redo(&mut world), ActionResult::Success));
+    let block = world.get::<BlockData>(blocks[0]).unwrap();
+    assert_eq!(block.min(), IVec3::X * 2);
+  }
+
+  #[test]
+  fn flip_fails_onto_other_blocks() {
+    let mut world = World::new();
+    let blocks = spawn_row(&mut world, 3);
+    // An L of the first block and one above the second, whose bounds flip the
+    // first block onto the unselected second one.
+    let mut block = world.get::<BlockData>(blocks[0]).unwrap().0.clone();
+    block.set_min(IVec3::new(1, 1, 0));
+    let above = world.spawn((BlockIndex(3), BlockData(block))).id();
+
+    let mut action = FlipAction {
+      entities: vec![blocks[0], above],
+      axis: GridAxis::X,
+    };
+    assert!(matches!(action.redo(&mut world), ActionResult::Failed(_)));
+    let block = world.get::<BlockData>(blocks[0]).unwrap();
+    assert_eq!(block.min(), IVec3::ZERO);
+  }
+
+  #[test]
+  fn rotate_fails_onto_other_blocks() {
+    let mut world = World::new();
+    let blocks = spawn_row(&mut world, 3);
+    // Turning the first two blocks about Z around their middle stands them
+    // upright, with one in the cell of the first.
+    let mut action =
+      RotateBlockAction::new(vec![blocks[0], blocks[1]], GridAxis::Z, 1);
+    assert!(matches!(action.redo(&mut world), ActionResult::Success));
+
+    // Turning them back lays them down again, but the third block is moved
+    // into the way first.
+    world
+      .get_mut::<BlockData>(blocks[2])
+      .unwrap()
+      .set_min(IVec3::X);
+    assert!(matches!(action.undo(&mut world), ActionResult::Failed(_)));
+  }
+
+  #[test]
+  fn translate_fails_out_of_frame_range() {
+    let mut world = World::new();
+    let blocks = spawn_row(&mut world, 1);
+    let mut action = TranslateAction {
+      entities: vec![blocks[0]],
+      delta: IVec3::Y * (i8::MAX as i32 + 1) * FRAME_SIZE as i32,
+    };
+    assert!(matches!(action.redo(&mut world), ActionResult::Failed(_)));
+    let block = world.get::<BlockData>(blocks[0]).unwrap();
+    assert_eq!(block.min(), IVec3::ZERO);
+  }
+
+  #[test]
+  fn paste_fails_onto_other_blocks() {
+    let mut world = World::new();
+    let blocks = spawn_row(&mut world, 2);
+    let block = world.get::<BlockData>(blocks[0]).unwrap().0.clone();
+
+    let mut action = InsertBlocksAction::paste(vec![block], IVec3::X);
+    assert!(matches!(action.redo(&mut world), ActionResult::Failed(_)));
+    assert_eq!(world.query::<&BlockData>().iter(&world).count(), 2);
+  }
 }
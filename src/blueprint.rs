@@ -1,28 +1,124 @@
-use bevy::{platform::collections::HashMap, prelude::*};
+use bevy::{
+  asset::{AssetLoader, LoadContext, io::Reader},
+  platform::collections::{HashMap, HashSet},
+  prelude::*,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+  FRAME_SIZE,
+  shape::{GridAxis, mirror_block_type, rotate_block_type},
+  symmetry::SymmetryPlane,
+};
+
 pub struct BlueprintPlugin;
 
 impl Plugin for BlueprintPlugin {
   fn build(&self, app: &mut App) {
     app
       .init_asset::<Blueprint>()
+      .init_asset_loader::<BlueprintLoader>()
       .init_state::<BlueprintState>()
       .init_resource::<LoadedBlueprint>()
+      .add_systems(
+        Update,
+        load_active_blueprint.run_if(in_state(BlueprintState::Unloaded)),
+      )
       .add_systems(PostUpdate, update_blueprint_state);
   }
 }
 
-#[derive(Deref, DerefMut, Resource)]
-pub struct LoadedBlueprint(pub Handle<Blueprint>);
+/// The paths of the blueprints to open, relative to the assets folder, such as
+/// those given on the command line.
+///
+/// Insert it before adding [`BlueprintPlugin`] for [`LoadedBlueprint`] to
+/// open them instead of the default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Resource)]
+pub struct BlueprintPaths(pub Vec<String>);
+
+impl Default for BlueprintPaths {
+  fn default() -> Self {
+    Self(vec!["blueprint.json".into()])
+  }
+}
+
+/// The blueprints open in the editor, of which the active one is displayed.
+///
+/// This dereferences to the handle of the active blueprint.
+#[derive(Resource)]
+pub struct LoadedBlueprint {
+  blueprints: Vec<Handle<Blueprint>>,
+  active: usize,
+}
+
+impl LoadedBlueprint {
+  /// Creates the resource with the first blueprint active.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `blueprints` is empty.
+  pub fn new(blueprints: Vec<Handle<Blueprint>>) -> Self {
+    assert!(
+      !blueprints.is_empty(),
+      "at least one blueprint must be open"
+    );
+    Self {
+      blueprints,
+      active: 0,
+    }
+  }
+
+  /// The handles of every open blueprint.
+  pub fn handles(&self) -> &[Handle<Blueprint>] {
+    &self.blueprints
+  }
+
+  /// The index of the active blueprint in [`Self::handles`].
+  pub fn active_index(&self) -> usize {
+    self.active
+  }
+
+  /// Makes the next open blueprint active, wrapping around after the last.
+  pub fn cycle(&mut self) {
+    self.active = (self.active + 1) % self.blueprints.len();
+  }
+}
+
+impl core::ops::Deref for LoadedBlueprint {
+  type Target = Handle<Blueprint>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.blueprints[self.active]
+  }
+}
 
 impl FromWorld for LoadedBlueprint {
   fn from_world(world: &mut World) -> Self {
+    let mut paths = world
+      .get_resource::<BlueprintPaths>()
+      .cloned()
+      .unwrap_or_default();
+    if paths.0.is_empty() {
+      paths = BlueprintPaths::default();
+    }
+
     let asset_server = world.resource::<AssetServer>();
-    Self(asset_server.load("blueprint.json"))
+    Self::new(
+      paths
+        .0
+        .into_iter()
+        .map(|path| asset_server.load(path))
+        .collect(),
+    )
   }
 }
 
+/// Whether the active blueprint is loaded and shown.
+///
+/// The scene is spawned on entering [`Self::Loaded`], and everything spawned
+/// for the blueprint is despawned on leaving it, such as when the file changes
+/// or another blueprint is switched to, so that a blueprint that fails to load
+/// doesn't leave the last one's scene behind.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, States)]
 pub enum BlueprintState {
   #[default]
@@ -31,22 +127,282 @@ pub enum BlueprintState {
 }
 
 pub fn update_blueprint_state(
+  state: Res<State<BlueprintState>>,
   mut blueprint_state: ResMut<NextState<BlueprintState>>,
   mut events: MessageReader<AssetEvent<Blueprint>>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
 ) {
+  // The other open blueprints load in the background, but only the active one
+  // drives the state.
+  let active = blueprint.id();
+
   for event in events.read() {
     match event {
-      AssetEvent::Modified { .. } | AssetEvent::Removed { .. } => {
+      AssetEvent::Modified { id } | AssetEvent::Removed { id }
+        if *id == active && *state.get() == BlueprintState::Loaded =>
+      {
         blueprint_state.set(BlueprintState::Unloaded)
       }
-      AssetEvent::LoadedWithDependencies { .. } => {
-        blueprint_state.set(BlueprintState::Loaded)
+      AssetEvent::LoadedWithDependencies { id } if *id == active => {
+        // Problems within the blueprint are reported, but are skipped over
+        // rather than refusing to show it.
+        if let Some(Err(error)) = blueprints.get(*id).map(Blueprint::validate) {
+          warn!("{error}");
+        }
+
+        if *state.get() == BlueprintState::Unloaded {
+          blueprint_state.set(BlueprintState::Loaded)
+        }
       }
       _ => {}
     }
   }
 }
 
+/// Loads the active blueprint if it has already finished loading, such as
+/// after switching to a blueprint that loaded in the background.
+pub fn load_active_blueprint(
+  asset_server: Res<AssetServer>,
+  mut blueprint_state: ResMut<NextState<BlueprintState>>,
+  blueprint: Res<LoadedBlueprint>,
+) {
+  if asset_server.is_loaded_with_dependencies(blueprint.id()) {
+    blueprint_state.set(BlueprintState::Loaded);
+  }
+}
+
+// Version 1 is the only format the editor has ever read, and there is no
+// description of an earlier one to migrate from, so other versions are refused
+// rather than migrated.
+
+/// The [`Blueprint::version`] this editor understands.
+pub const BLUEPRINT_VERSION: u8 = 1;
+/// The [`BlueprintData::version`] this editor understands.
+pub const BLUEPRINT_DATA_VERSION: u8 = 1;
+
+/// A [`Blueprint`] uses a format version this editor doesn't understand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnsupportedVersion {
+  /// The [`Blueprint::version`] is unsupported.
+  Blueprint { found: u8, expected: u8 },
+  /// The [`BlueprintData::version`] is unsupported.
+  Data { found: u8, expected: u8 },
+}
+
+impl core::fmt::Display for UnsupportedVersion {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::Blueprint { found, expected } => write!(
+        f,
+        "blueprint version {found} is not supported, expected {expected}"
+      ),
+      Self::Data { found, expected } => write!(
+        f,
+        "blueprint data version {found} is not supported, expected {expected}"
+      ),
+    }
+  }
+}
+
+impl core::error::Error for UnsupportedVersion {}
+
+/// An error from [`BlueprintData::remove_component`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RemoveComponentError {
+  /// There is no component at the index.
+  OutOfRange { index: usize, len: usize },
+  /// The index doesn't fit in the `u8` that pipes and composite builds use to
+  /// refer to components.
+  Unreferenceable(usize),
+}
+
+impl core::fmt::Display for RemoveComponentError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::OutOfRange { index, len } => write!(
+        f,
+        "cannot remove component {index}, as there are only {len}"
+      ),
+      Self::Unreferenceable(index) => write!(
+        f,
+        "cannot remove component {index}, as it is past index {}",
+        u8::MAX
+      ),
+    }
+  }
+}
+
+impl core::error::Error for RemoveComponentError {}
+
+/// An error from loading, validating or saving a [`Blueprint`].
+#[derive(Debug)]
+pub enum BlueprintError {
+  /// The blueprint file could not be read or written.
+  Io(std::io::Error),
+  /// The blueprint is not valid JSON, or doesn't match the format.
+  Parse(serde_json::Error),
+  UnsupportedVersion(UnsupportedVersion),
+  /// The blueprint parsed, but its contents are inconsistent.
+  Validation(Vec<BlueprintIssue>),
+}
+
+impl core::fmt::Display for BlueprintError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::Io(error) => write!(f, "could not access blueprint: {error}"),
+      Self::Parse(error) => write!(f, "could not parse blueprint: {error}"),
+      Self::UnsupportedVersion(error) => error.fmt(f),
+      Self::Validation(issues) => {
+        write!(f, "blueprint has {} problems", issues.len())?;
+        for issue in issues {
+          write!(f, "; {issue}")?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+impl core::error::Error for BlueprintError {
+  fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+    match self {
+      Self::Io(error) => Some(error),
+      Self::Parse(error) => Some(error),
+      Self::UnsupportedVersion(error) => Some(error),
+      Self::Validation(_) => None,
+    }
+  }
+}
+
+impl From<std::io::Error> for BlueprintError {
+  fn from(error: std::io::Error) -> Self {
+    Self::Io(error)
+  }
+}
+
+impl From<serde_json::Error> for BlueprintError {
+  fn from(error: serde_json::Error) -> Self {
+    Self::Parse(error)
+  }
+}
+
+impl From<UnsupportedVersion> for BlueprintError {
+  fn from(error: UnsupportedVersion) -> Self {
+    Self::UnsupportedVersion(error)
+  }
+}
+
+/// An inconsistency in a [`Blueprint`] found by [`Blueprint::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BlueprintIssue {
+  /// An index refers to an element past the end of its list.
+  IndexOutOfBounds {
+    /// What holds the index, such as `"pipe 3"`.
+    referrer: String,
+    /// The list the index is into, such as `"components"`.
+    list: &'static str,
+    index: usize,
+    len: usize,
+  },
+  /// A block reaches past the far side of its frame.
+  BlockOutsideFrame {
+    /// The index of the block in [`BlueprintData::blocks`].
+    index: usize,
+    /// How many blocks it reaches past the frame along each axis.
+    overflow: IVec3,
+  },
+  /// A block has a type past the known ones.
+  UnknownBlockType {
+    /// The index of the block in [`BlueprintData::blocks`].
+    index: usize,
+    type_id: u8,
+  },
+  /// Two blocks cover some of the same cells.
+  OverlappingBlocks {
+    /// The indices of the blocks in [`BlueprintData::blocks`], lowest first.
+    indices: (usize, usize),
+    /// The position of the first block's frame on the frame grid.
+    frame: IVec3,
+  },
+}
+
+impl core::fmt::Display for BlueprintIssue {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::IndexOutOfBounds {
+        referrer,
+        list,
+        index,
+        len,
+      } => write!(
+        f,
+        "{referrer} refers to {list} index {index}, but there are only {len}"
+      ),
+      Self::BlockOutsideFrame { index, overflow } => write!(
+        f,
+        "block {index} reaches past its frame by {} × {} × {} blocks",
+        overflow.x, overflow.y, overflow.z
+      ),
+      Self::UnknownBlockType { index, type_id } => write!(
+        f,
+        "block {index} has type {type_id}, but there are only \
+         {BLOCK_TYPE_COUNT} types"
+      ),
+      Self::OverlappingBlocks {
+        indices: (a, b),
+        frame,
+      } => write!(
+        f,
+        "blocks {a} and {b} overlap in the frame at ({}, {}, {})",
+        frame.x, frame.y, frame.z
+      ),
+    }
+  }
+}
+
+/// Loads [`Blueprint`]s from JSON, refusing unsupported format versions.
+#[derive(Debug, Default, TypePath)]
+pub struct BlueprintLoader;
+
+impl AssetLoader for BlueprintLoader {
+  type Asset = Blueprint;
+  type Settings = ();
+  type Error = BlueprintError;
+
+  async fn load(
+    &self,
+    reader: &mut dyn Reader,
+    _settings: &Self::Settings,
+    _load_context: &mut LoadContext<'_>,
+  ) -> Result<Self::Asset, Self::Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+
+    let blueprint = serde_json::from_slice::<Blueprint>(&bytes)?;
+    blueprint.check_version()?;
+    Ok(blueprint)
+  }
+
+  fn extensions(&self) -> &[&str] {
+    &["json"]
+  }
+}
+
+/// Returns the frame containing the world-space point `world`, and the cell
+/// within that frame, with frames `frame_size` blocks wide.
+///
+/// This is the inverse of placing a block by its frame and position, so any
+/// point inside a block gives back the block's frame and position. The cell is
+/// clamped to the frame, in case rounding puts it on the far edge.
+pub fn world_to_frame_pos(world: Vec3, frame_size: f32) -> (IVec3, UVec3) {
+  let frame = (world / frame_size).floor();
+  let pos = (world - frame * frame_size)
+    .floor()
+    .clamp(Vec3::ZERO, Vec3::splat(frame_size - 1.0));
+  (frame.as_ivec3(), pos.as_uvec3())
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Coords {
   pub x: f64,
@@ -54,6 +410,13 @@ pub struct Coords {
   pub z: f64,
 }
 
+impl Coords {
+  /// Converts the coordinates to a [`Vec3`] in world space.
+  pub fn to_vec3(&self) -> Vec3 {
+    Vec3::new(self.x as f32, self.y as f32, self.z as f32)
+  }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct CoordsW {
   pub w: f64,
@@ -62,6 +425,55 @@ pub struct CoordsW {
   pub z: f64,
 }
 
+impl CoordsW {
+  /// Converts the orientation to a [`Quat`].
+  ///
+  /// Archean stores quaternions in `(w, x, y, z)` order, whereas [`Quat`] is
+  /// `(x, y, z, w)`, so the components are reordered. Both use a right-handed,
+  /// Y-up coordinate system, so no axis is flipped. The result is normalized,
+  /// and a zero quaternion becomes [`Quat::IDENTITY`].
+  pub fn to_quat(&self) -> Quat {
+    let quat = Quat::from_xyzw(
+      self.x as f32,
+      self.y as f32,
+      self.z as f32,
+      self.w as f32,
+    );
+    if quat.length_squared() > f32::EPSILON {
+      quat.normalize()
+    } else {
+      Quat::IDENTITY
+    }
+  }
+}
+
+/// The number of block types, so [`Block::type`] is below this. Types without
+/// a mesh are rendered as cubes.
+///
+/// [`Block::type`]: Block::r#type
+pub const BLOCK_TYPE_COUNT: u8 = 80;
+
+/// Returns the outward normal of the face whose color is at `face` in
+/// [`Block::colors`]. The faces come in pairs along X, Y and Z, each pair in
+/// the same order.
+fn face_normal(face: usize) -> IVec3 {
+  let unit = [IVec3::X, IVec3::Y, IVec3::Z][face / 2];
+  if face.is_multiple_of(2) { unit } else { -unit }
+}
+
+/// Returns the face whose outward normal is `normal`, the inverse of
+/// [`face_normal`].
+fn face_of_normal(normal: IVec3) -> usize {
+  let axis = if normal.x != 0 {
+    0
+  } else if normal.y != 0 {
+    1
+  } else {
+    2
+  };
+  axis * 2 + (normal.element_sum() < 0) as usize
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
   pub colors: (u8, u8, u8, u8, u8, u8, u8),
@@ -79,6 +491,213 @@ pub struct Block {
   pub r#type: u8,
 }
 
+impl Block {
+  /// The position of the block's frame on the frame grid.
+  pub fn frame(&self) -> IVec3 {
+    IVec3::new(
+      self.frame_x as i32,
+      self.frame_y as i32,
+      self.frame_z as i32,
+    )
+  }
+
+  /// The corner of the block nearest the origin, in blocks.
+  pub fn min(&self) -> IVec3 {
+    self.frame() * FRAME_SIZE as i32
+      + IVec3::new(self.pos_x as i32, self.pos_y as i32, self.pos_z as i32)
+  }
+
+  /// Moves the block so that its corner nearest the origin is at `min`, in
+  /// blocks, choosing the frame that contains it.
+  pub fn set_min(&mut self, min: IVec3) {
+    let frame = min.div_euclid(IVec3::splat(FRAME_SIZE as i32));
+    let pos = min.rem_euclid(IVec3::splat(FRAME_SIZE as i32));
+    (self.frame_x, self.frame_y, self.frame_z) =
+      (frame.x as i8, frame.y as i8, frame.z as i8);
+    (self.pos_x, self.pos_y, self.pos_z) =
+      (pos.x as u8, pos.y as u8, pos.z as u8);
+  }
+
+  /// Moves the block as [`Self::set_min`] does, unless the frame containing
+  /// `min` is out of range, in which case the block is left as it is and
+  /// `false` is returned.
+  pub fn try_set_min(&mut self, min: IVec3) -> bool {
+    let frame = min.div_euclid(IVec3::splat(FRAME_SIZE as i32));
+    let in_range = |frame: i32| i8::try_from(frame).is_ok();
+    if !(in_range(frame.x) && in_range(frame.y) && in_range(frame.z)) {
+      return false;
+    }
+    self.set_min(min);
+    true
+  }
+
+  /// Whether the block and `other` cover some of the same cells.
+  pub fn overlaps(&self, other: &Block) -> bool {
+    let (min, max) = (self.min(), self.min() + self.size());
+    let (other_min, other_max) = (other.min(), other.min() + other.size());
+    min.cmplt(other_max).all() && other_min.cmplt(max).all()
+  }
+
+  /// Mirrors the block across the plane perpendicular to `axis` at half of
+  /// `twice_plane`, in blocks, mirroring its shape and face colors to match.
+  ///
+  /// The plane is given doubled so that it can lie between cells. Returns
+  /// whether the shape could be mirrored; if not, the block is only moved.
+  /// Returns [`None`] instead, leaving the block as it is, if it would end up
+  /// in a frame out of range.
+  pub fn mirror(&mut self, axis: GridAxis, twice_plane: i32) -> Option<bool> {
+    // Mirror the far corner of the block along the axis to get its new near
+    // corner, keeping the other coordinates.
+    let unit = axis.unit();
+    let far = self.min() + self.size();
+    let min = (twice_plane - far) * unit + self.min() * (IVec3::ONE - unit);
+    if !self.try_set_min(min) {
+      return None;
+    }
+
+    // The faces facing either way along the axis trade places.
+    let colors = &mut self.colors;
+    match axis {
+      GridAxis::X => core::mem::swap(&mut colors.0, &mut colors.1),
+      GridAxis::Y => core::mem::swap(&mut colors.2, &mut colors.3),
+      GridAxis::Z => core::mem::swap(&mut colors.4, &mut colors.5),
+    }
+
+    match mirror_block_type(self.r#type, axis) {
+      Some(type_id) => {
+        self.r#type = type_id;
+        Some(true)
+      }
+      None => Some(false),
+    }
+  }
+
+  /// Rotates the block by `turns` quarter turns counterclockwise about `axis`
+  /// around the grid corner `pivot`, in blocks, rotating its shape and face
+  /// colors to match. Negative `turns` rotate clockwise.
+  ///
+  /// Everything stays on the grid, so rotating back by `-turns` restores the
+  /// block exactly. Returns whether the shape could be rotated; if not, only
+  /// its box is. Returns [`None`] instead, leaving the block as it is, if it
+  /// would end up in a frame out of range.
+  pub fn rotate(
+    &mut self,
+    axis: GridAxis,
+    turns: i32,
+    pivot: IVec3,
+  ) -> Option<bool> {
+    let turns = turns.rem_euclid(4);
+    let rotate = |v: IVec3| (0..turns).fold(v, |v, _| axis.quarter_turn(v));
+
+    let near = rotate(self.min() - pivot);
+    let far = rotate(self.min() + self.size() - pivot);
+    if !self.try_set_min(pivot + near.min(far)) {
+      return None;
+    }
+    let size = (far - near).abs() - IVec3::ONE;
+    (self.size_x, self.size_y, self.size_z) =
+      (size.x as u8, size.y as u8, size.z as u8);
+
+    // Each face moves to wherever its normal is rotated to.
+    let (a, b, c, d, e, f, g) = self.colors;
+    let faces = [a, b, c, d, e, f];
+    let mut rotated = faces;
+    for (face, color) in faces.into_iter().enumerate() {
+      rotated[face_of_normal(rotate(face_normal(face)))] = color;
+    }
+    let [a, b, c, d, e, f] = rotated;
+    self.colors = (a, b, c, d, e, f, g);
+
+    match rotate_block_type(self.r#type, axis, turns) {
+      Some(type_id) => {
+        self.r#type = type_id;
+        Some(true)
+      }
+      None => Some(false),
+    }
+  }
+
+  /// The size of the block, in blocks.
+  pub fn size(&self) -> IVec3 {
+    IVec3::new(
+      self.size_x as i32 + 1,
+      self.size_y as i32 + 1,
+      self.size_z as i32 + 1,
+    )
+  }
+
+  /// How many blocks the block reaches past the far side of its frame along
+  /// each axis, which is zero for blocks that fit.
+  pub fn frame_overflow(&self) -> IVec3 {
+    let pos =
+      IVec3::new(self.pos_x as i32, self.pos_y as i32, self.pos_z as i32);
+    (pos + self.size() - FRAME_SIZE as i32).max(IVec3::ZERO)
+  }
+
+  /// Whether the block covers the grid cell `cell`, in blocks.
+  pub fn contains_cell(&self, cell: IVec3) -> bool {
+    let min = self.min();
+    cell.cmpge(min).all() && cell.cmplt(min + self.size()).all()
+  }
+
+  /// The minimum and maximum corners of the block's box in world space, with
+  /// frames `frame_size` blocks wide.
+  pub fn aabb(&self, frame_size: f32) -> (Vec3, Vec3) {
+    let frame = Vec3::new(
+      self.frame_x as f32,
+      self.frame_y as f32,
+      self.frame_z as f32,
+    );
+    let pos =
+      Vec3::new(self.pos_x as f32, self.pos_y as f32, self.pos_z as f32);
+    let min = frame * frame_size + pos;
+    (min, min + self.size().as_vec3())
+  }
+
+  /// The transform of the block's mesh in world space: a unit cube scaled to
+  /// the block's size and centered in its box.
+  pub fn world_transform(&self) -> Transform {
+    let size = self.size().as_vec3();
+    Transform::from_translation(self.min().as_vec3() + size * 0.5)
+      .with_scale(size)
+  }
+
+  /// Returns the distance along the ray from `origin` in direction `dir` at
+  /// which it first enters the block's box, or zero if `origin` is inside it.
+  ///
+  /// `dir` must be normalized. A ray grazing an edge or face counts as a hit.
+  pub fn raycast(
+    &self,
+    origin: Vec3,
+    dir: Vec3,
+    frame_size: f32,
+  ) -> Option<f32> {
+    let (min, max) = self.aabb(frame_size);
+    let mut near = 0.0_f32;
+    let mut far = f32::INFINITY;
+
+    for axis in 0..3 {
+      if dir[axis] == 0.0 {
+        // The ray is parallel to this slab, so it has to start within it.
+        if origin[axis] < min[axis] || origin[axis] > max[axis] {
+          return None;
+        }
+        continue;
+      }
+
+      let a = (min[axis] - origin[axis]) / dir[axis];
+      let b = (max[axis] - origin[axis]) / dir[axis];
+      near = near.max(a.min(b));
+      far = far.min(a.max(b));
+      if near > far {
+        return None;
+      }
+    }
+
+    Some(near)
+  }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ColorMaterial {
   pub r: u8,
@@ -89,6 +708,35 @@ pub struct ColorMaterial {
   pub roughness: u8,
 }
 
+impl ColorMaterial {
+  /// Converts the color channels to a [`Color`], using `opacity` as alpha.
+  pub fn to_color(&self) -> Color {
+    Color::srgba_u8(self.r, self.g, self.b, self.opacity)
+  }
+}
+
+impl From<&ColorMaterial> for StandardMaterial {
+  fn from(color: &ColorMaterial) -> Self {
+    Self {
+      base_color: color.to_color(),
+      metallic: color.metallic as f32 / u8::MAX as f32,
+      perceptual_roughness: color.roughness as f32 / u8::MAX as f32,
+      alpha_mode: if color.opacity == u8::MAX {
+        AlphaMode::Opaque
+      } else {
+        AlphaMode::Blend
+      },
+      ..Default::default()
+    }
+  }
+}
+
+impl From<ColorMaterial> for StandardMaterial {
+  fn from(color: ColorMaterial) -> Self {
+    Self::from(&color)
+  }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ColorRGB {
   pub r: u8,
@@ -111,6 +759,17 @@ pub enum ColorOrZero {
   Zero(u8),
 }
 
+impl ColorOrZero {
+  /// Returns the color, or [`None`] for an empty palette slot.
+  pub fn resolve(&self) -> Option<ColorMaterial> {
+    match self {
+      Self::Color(color) => Some(*color),
+      Self::Zero(_) => None,
+    }
+  }
+}
+
+/// Any JSON value, such as the configuration of a component.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Value {
@@ -123,6 +782,139 @@ pub enum Value {
   Null(()),
 }
 
+impl Value {
+  /// Returns the value if it is a bool.
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      Self::Bool(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value if it is an integer.
+  pub fn as_int(&self) -> Option<isize> {
+    match self {
+      Self::Int(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value if it is a number, converting integers to floats.
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      Self::Int(value) => Some(*value as f64),
+      Self::Float(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value if it is a string.
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Self::String(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value if it is a map.
+  pub fn as_map(&self) -> Option<&HashMap<String, Value>> {
+    match self {
+      Self::Map(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value if it is a list.
+  pub fn as_vec(&self) -> Option<&[Value]> {
+    match self {
+      Self::Vec(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Returns whether the value is null.
+  pub fn is_null(&self) -> bool {
+    matches!(self, Self::Null(()))
+  }
+
+  /// Returns the entry for `key` if the value is a map containing it.
+  pub fn get(&self, key: &str) -> Option<&Value> {
+    self.as_map()?.get(key)
+  }
+
+  /// Returns the item at `index` if the value is a list long enough to have
+  /// it.
+  pub fn index(&self, index: usize) -> Option<&Value> {
+    self.as_vec()?.get(index)
+  }
+}
+
+impl From<bool> for Value {
+  fn from(value: bool) -> Self {
+    Self::Bool(value)
+  }
+}
+
+impl From<isize> for Value {
+  fn from(value: isize) -> Self {
+    Self::Int(value)
+  }
+}
+
+impl From<i32> for Value {
+  fn from(value: i32) -> Self {
+    Self::Int(value as isize)
+  }
+}
+
+impl From<f64> for Value {
+  fn from(value: f64) -> Self {
+    Self::Float(value)
+  }
+}
+
+impl From<f32> for Value {
+  fn from(value: f32) -> Self {
+    Self::Float(value as f64)
+  }
+}
+
+impl From<String> for Value {
+  fn from(value: String) -> Self {
+    Self::String(value)
+  }
+}
+
+impl From<&str> for Value {
+  fn from(value: &str) -> Self {
+    Self::String(value.to_owned())
+  }
+}
+
+impl From<HashMap<String, Value>> for Value {
+  fn from(value: HashMap<String, Value>) -> Self {
+    Self::Map(value)
+  }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+  fn from(value: Vec<T>) -> Self {
+    Self::Vec(value.into_iter().map(Into::into).collect())
+  }
+}
+
+impl From<()> for Value {
+  fn from((): ()) -> Self {
+    Self::Null(())
+  }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+  fn from(value: Option<T>) -> Self {
+    value.map_or(Self::Null(()), Into::into)
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Occupancy {
   pub frame_x: i8,
@@ -136,6 +928,27 @@ pub struct Occupancy {
   pub size_z: u8,
 }
 
+impl Occupancy {
+  /// The corners of the box of cells the occupancy fills, in blocks, with
+  /// its size encoded the same way as a [`Block`]'s.
+  pub fn aabb(&self) -> (Vec3, Vec3) {
+    let frame = Vec3::new(
+      self.frame_x as f32,
+      self.frame_y as f32,
+      self.frame_z as f32,
+    );
+    let pos =
+      Vec3::new(self.pos_x as f32, self.pos_y as f32, self.pos_z as f32);
+    let size = Vec3::new(
+      self.size_x as f32 + 1.0,
+      self.size_y as f32 + 1.0,
+      self.size_z as f32 + 1.0,
+    );
+    let min = frame * FRAME_SIZE + pos;
+    (min, min + size)
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Component {
   #[serde(default)]
@@ -149,6 +962,15 @@ pub struct Component {
   pub r#type: String,
 }
 
+impl Component {
+  /// The world-space transform of the component, from its position and
+  /// orientation.
+  pub fn transform(&self) -> Transform {
+    Transform::from_translation(self.position.to_vec3())
+      .with_rotation(self.orientation.to_quat())
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Frame {
   pub beams: (u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8),
@@ -157,6 +979,40 @@ pub struct Frame {
   pub frame_z: i8,
 }
 
+impl Frame {
+  /// The frame's position on the frame grid.
+  pub fn coords(&self) -> IVec3 {
+    IVec3::new(
+      self.frame_x as i32,
+      self.frame_y as i32,
+      self.frame_z as i32,
+    )
+  }
+
+  /// The value of each nonzero beam slot, with the axis the edge it is on runs
+  /// along and the corner of the frame the edge starts from, as 0 or 1 on each
+  /// axis.
+  ///
+  /// The format doesn't say which slot is which edge, so the slots are taken
+  /// to be the four edges along X, then along Y, then along Z, each four
+  /// ordered by their other two coordinates with the first varying fastest.
+  pub fn beams(&self) -> impl Iterator<Item = (u8, GridAxis, IVec3)> {
+    let (a, b, c, d, e, f, g, h, i, j, k, l) = self.beams;
+    [a, b, c, d, e, f, g, h, i, j, k, l]
+      .into_iter()
+      .enumerate()
+      .filter(|(_, value)| *value != 0)
+      .map(|(slot, value)| {
+        let (first, second) = ((slot & 1) as i32, (slot >> 1 & 1) as i32);
+        match slot / 4 {
+          0 => (value, GridAxis::X, IVec3::new(0, first, second)),
+          1 => (value, GridAxis::Y, IVec3::new(first, 0, second)),
+          _ => (value, GridAxis::Z, IVec3::new(first, second, 0)),
+        }
+      })
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Label {
   pub align_center: u8,
@@ -212,6 +1068,92 @@ pub struct CompositeBuild {
   pub slave_build_id: u8,
 }
 
+/// A component hosting a sub-build, resolved from a [`CompositeBuild`].
+#[derive(Debug, Clone, Copy)]
+pub struct CompositeLink<'a> {
+  /// The index of the component in [`BlueprintData::components`].
+  pub component_index: usize,
+  pub component: &'a Component,
+  /// The sub-build's ID, which refers to a build outside of this blueprint.
+  pub slave_build_id: u8,
+}
+
+/// A door, with the fields the editor reads named and the rest kept in
+/// `extra`, so that doors are saved as they were loaded.
+///
+/// Every named field is optional, as not every door has been seen with all of
+/// them. Doors are saved with their keys sorted, as the game saves them,
+/// rather than with the named fields first.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Door {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub position: Option<Coords>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub orientation: Option<CoordsW>,
+  /// The size of the door along each of its axes, in blocks.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub size: Option<Coords>,
+  /// Whether the door is open, or how far, depending on its kind. A `null`
+  /// state is kept as [`Value::Null`], so that it is saved again.
+  #[serde(default, deserialize_with = "deserialize_present")]
+  pub state: Option<Value>,
+  /// The index of the component the door is linked to.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub component: Option<u8>,
+  #[serde(flatten)]
+  pub extra: HashMap<String, Value>,
+}
+
+/// Deserializes a field that is present as [`Some`], even if it is `null`.
+fn deserialize_present<'de, D, T>(
+  deserializer: D,
+) -> Result<Option<T>, D::Error>
+where
+  D: serde::Deserializer<'de>,
+  T: Deserialize<'de>,
+{
+  T::deserialize(deserializer).map(Some)
+}
+
+impl Serialize for Door {
+  fn serialize<S: serde::Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    use serde::ser::Error;
+
+    let named = [
+      ("component", self.component.map(serde_json::to_value)),
+      ("orientation", self.orientation.map(serde_json::to_value)),
+      ("position", self.position.map(serde_json::to_value)),
+      ("size", self.size.map(serde_json::to_value)),
+      ("state", self.state.as_ref().map(serde_json::to_value)),
+    ];
+    let extra = self
+      .extra
+      .iter()
+      .map(|(key, value)| (key.as_str(), Some(serde_json::to_value(value))));
+
+    let mut entries = std::collections::BTreeMap::new();
+    for (key, value) in named.into_iter().chain(extra) {
+      if let Some(value) = value {
+        entries.insert(key, value.map_err(S::Error::custom)?);
+      }
+    }
+    entries.serialize(serializer)
+  }
+}
+
+impl Door {
+  /// The world-space transform of the door, from its position and
+  /// orientation, or [`None`] if it has no position.
+  pub fn transform(&self) -> Option<Transform> {
+    let position = self.position?.to_vec3();
+    let rotation = self.orientation.map_or(Quat::IDENTITY, |o| o.to_quat());
+    Some(Transform::from_translation(position).with_rotation(rotation))
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlueprintData {
   pub alias: String,
@@ -219,7 +1161,7 @@ pub struct BlueprintData {
   pub colors: Vec<ColorOrZero>,
   pub components: Vec<Component>,
   pub composite_builds: Vec<CompositeBuild>,
-  pub doors: Vec<()>,
+  pub doors: Vec<Door>,
   pub frames: Vec<Frame>,
   pub labels: Vec<Label>,
   pub pipes: Vec<Pipe>,
@@ -228,6 +1170,275 @@ pub struct BlueprintData {
   pub version: u8,
 }
 
+impl BlueprintData {
+  /// Returns the block at `index`, or [`None`] if the index is out of range.
+  pub fn block(&self, index: usize) -> Option<&Block> {
+    self.blocks.get(index)
+  }
+
+  /// Returns the frame at `index`, or [`None`] if the index is out of range.
+  pub fn frame(&self, index: usize) -> Option<&Frame> {
+    self.frames.get(index)
+  }
+
+  /// Returns the component at `index`, or [`None`] if the index is out of
+  /// range, such as a pipe endpoint referencing a missing component.
+  pub fn component(&self, index: usize) -> Option<&Component> {
+    self.components.get(index)
+  }
+
+  /// Returns the indices of the blocks in each frame, keyed by the position of
+  /// the frame on the frame grid.
+  ///
+  /// Frames without blocks are left out, and blocks may be in frames missing
+  /// from [`BlueprintData::frames`].
+  pub fn blocks_by_frame(&self) -> HashMap<IVec3, Vec<usize>> {
+    let mut frames = HashMap::<IVec3, Vec<usize>>::new();
+    for (index, block) in self.blocks.iter().enumerate() {
+      frames.entry(block.frame()).or_default().push(index);
+    }
+    frames
+  }
+
+  /// Returns the pairs of blocks that cover some of the same cells, as their
+  /// indices in [`Self::blocks`], lowest first and sorted.
+  ///
+  /// Blocks are bucketed by the frames their boxes touch, so only blocks
+  /// sharing a frame are compared.
+  pub fn overlaps(&self) -> Vec<(usize, usize)> {
+    let frame_size = IVec3::splat(FRAME_SIZE as i32);
+    let bounds = |block: &Block| (block.min(), block.min() + block.size());
+
+    let mut buckets = HashMap::<IVec3, Vec<usize>>::new();
+    for (index, block) in self.blocks.iter().enumerate() {
+      let (min, max) = bounds(block);
+      let (min, max) =
+        (min.div_euclid(frame_size), (max - 1).div_euclid(frame_size));
+      for x in min.x..=max.x {
+        for y in min.y..=max.y {
+          for z in min.z..=max.z {
+            buckets.entry(IVec3::new(x, y, z)).or_default().push(index);
+          }
+        }
+      }
+    }
+
+    let mut overlaps = HashSet::<(usize, usize)>::new();
+    for indices in buckets.values() {
+      for (i, &a) in indices.iter().enumerate() {
+        for &b in &indices[i + 1..] {
+          if self.blocks[a].overlaps(&self.blocks[b]) {
+            overlaps.insert((a, b));
+          }
+        }
+      }
+    }
+
+    let mut overlaps = overlaps.into_iter().collect::<Vec<_>>();
+    overlaps.sort();
+    overlaps
+  }
+
+  /// Returns the positions on the frame grid of the frames in
+  /// [`BlueprintData::frames`].
+  pub fn frame_coords(&self) -> HashSet<IVec3> {
+    self.frames.iter().map(Frame::coords).collect()
+  }
+
+  /// Removes the component at `index`, along with the pipes and composite
+  /// builds attached to it, and renumbers the references to the components
+  /// after it.
+  ///
+  /// Leaves the blueprint as is and returns an error if there is no
+  /// component at `index`, or if it is past the indices that pipes and
+  /// composite builds can refer to.
+  pub fn remove_component(
+    &mut self,
+    index: usize,
+  ) -> Result<Component, RemoveComponentError> {
+    let len = self.components.len();
+    if index >= len {
+      return Err(RemoveComponentError::OutOfRange { index, len });
+    }
+    let Ok(reference) = u8::try_from(index) else {
+      return Err(RemoveComponentError::Unreferenceable(index));
+    };
+    let component = self.components.remove(index);
+
+    let index = reference;
+    let renumber = |reference: &mut u8| {
+      if *reference > index {
+        *reference -= 1;
+      }
+    };
+
+    self
+      .pipes
+      .retain(|pipe| pipe.a_component != index && pipe.b_component != index);
+    for pipe in self.pipes.iter_mut() {
+      renumber(&mut pipe.a_component);
+      renumber(&mut pipe.b_component);
+    }
+
+    self
+      .composite_builds
+      .retain(|build| build.component != index);
+    for build in self.composite_builds.iter_mut() {
+      renumber(&mut build.component);
+    }
+
+    Ok(component)
+  }
+
+  /// Returns the corner nearest the origin, the far corner and the size of
+  /// the box enclosing the blueprint, in blocks, as stored in
+  /// [`Blueprint::box_min`], [`Blueprint::box_max`] and
+  /// [`Blueprint::box_size`].
+  ///
+  /// The box encloses every block, and every frame without a block in it.
+  /// Frames holding blocks don't count, as they would widen the box to whole
+  /// frames.
+  pub fn compute_bounds(&self) -> (Coords, Coords, Coords) {
+    let occupied = self.blocks.iter().map(Block::frame).collect::<HashSet<_>>();
+    let frame_size = IVec3::splat(FRAME_SIZE as i32);
+
+    let blocks = self
+      .blocks
+      .iter()
+      .map(|block| (block.min(), block.min() + block.size()));
+    let frames = self
+      .frames
+      .iter()
+      .map(Frame::coords)
+      .filter(|coords| !occupied.contains(coords))
+      .map(|coords| (coords * frame_size, (coords + 1) * frame_size));
+    let (min, max) = blocks
+      .chain(frames)
+      .reduce(|(min, max), (other_min, other_max)| {
+        (min.min(other_min), max.max(other_max))
+      })
+      .unwrap_or_default();
+
+    let coords = |v: IVec3| Coords {
+      x: v.x as f64,
+      y: v.y as f64,
+      z: v.z as f64,
+    };
+    (coords(min), coords(max), coords(max - min))
+  }
+
+  /// Returns the mass of the blocks, as the volume of each block in cells
+  /// times the density of its material, indexed by [`Block::material`].
+  /// Materials past the end of `material_densities` have a density of one.
+  pub fn compute_mass(&self, material_densities: &[f32]) -> f32 {
+    self
+      .blocks
+      .iter()
+      .map(|block| {
+        let density = material_densities
+          .get(block.material as usize)
+          .copied()
+          .unwrap_or(1.0);
+        block.size().element_product() as f32 * density
+      })
+      .sum()
+  }
+
+  /// Removes the pipes and composite builds referring to components that
+  /// don't exist, so that [`Blueprint::validate`] passes, and returns how
+  /// many were removed.
+  ///
+  /// Compacting a blueprint that is already compact changes nothing.
+  pub fn compact(&mut self) -> usize {
+    let len = self.components.len();
+    let exists = |component: u8| (component as usize) < len;
+    let before = self.pipes.len() + self.composite_builds.len();
+
+    self
+      .pipes
+      .retain(|pipe| exists(pipe.a_component) && exists(pipe.b_component));
+    self
+      .composite_builds
+      .retain(|build| exists(build.component));
+
+    before - (self.pipes.len() + self.composite_builds.len())
+  }
+
+  /// Returns the plane the blueprint is built symmetrically about, if any.
+  ///
+  /// [`BlueprintData::symmetry_axis`] is taken to be 0 for none, or 1 to 3 for
+  /// a plane perpendicular to the X, Y or Z axis, placed along that axis by
+  /// [`BlueprintData::symmetry_axis_offset`].
+  pub fn symmetry_plane(&self) -> Option<SymmetryPlane> {
+    let offset = &self.symmetry_axis_offset;
+    let (axis, offset) = match self.symmetry_axis {
+      1 => (GridAxis::X, offset.x),
+      2 => (GridAxis::Y, offset.y),
+      3 => (GridAxis::Z, offset.z),
+      _ => return None,
+    };
+    Some(SymmetryPlane {
+      axis,
+      twice_offset: (offset * 2.0).round() as i32,
+    })
+  }
+
+  /// Returns the index of the nearest block hit by the ray from `origin` in
+  /// direction `dir`, and the distance to it, with frames `frame_size` blocks
+  /// wide.
+  ///
+  /// This intersects each block's box analytically, so unlike picking it works
+  /// without rendering and ignores the shape of the block's mesh.
+  pub fn raycast(
+    &self,
+    origin: Vec3,
+    dir: Vec3,
+    frame_size: f32,
+  ) -> Option<(usize, f32)> {
+    let dir = dir.try_normalize()?;
+    self
+      .blocks
+      .iter()
+      .enumerate()
+      .filter_map(|(index, block)| {
+        block
+          .raycast(origin, dir, frame_size)
+          .map(|distance| (index, distance))
+      })
+      .min_by(|(_, a), (_, b)| a.total_cmp(b))
+  }
+
+  /// Returns the components hosting sub-builds, skipping composite builds
+  /// whose component doesn't exist.
+  pub fn composite_links(&self) -> impl Iterator<Item = CompositeLink<'_>> {
+    self.composite_builds.iter().filter_map(|build| {
+      let component_index = build.component as usize;
+      self
+        .component(component_index)
+        .map(|component| CompositeLink {
+          component_index,
+          component,
+          slave_build_id: build.slave_build_id,
+        })
+    })
+  }
+
+  /// Returns the palette color at `index`, or [`None`] if the index is out of
+  /// range or the slot is empty.
+  pub fn color(&self, index: u8) -> Option<ColorMaterial> {
+    self
+      .colors
+      .get(index as usize)
+      .and_then(ColorOrZero::resolve)
+  }
+
+  /// Returns the primary color of a block, which is the palette color
+  /// referenced by the first entry of [`Block::colors`].
+  pub fn block_color(&self, block: &Block) -> Option<ColorMaterial> {
+    self.color(block.colors.0)
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Asset, TypePath)]
 pub struct Blueprint {
   pub author: String,
@@ -240,3 +1451,517 @@ pub struct Blueprint {
   pub r#type: String,
   pub version: u8,
 }
+
+impl Blueprint {
+  /// Sets [`Self::box_min`], [`Self::box_max`] and [`Self::box_size`] from
+  /// the data, as they go stale when blocks are edited.
+  pub fn update_bounds(&mut self) {
+    (self.box_min, self.box_max, self.box_size) = self.data.compute_bounds();
+  }
+
+  /// Checks that the blueprint and its data use format versions this editor
+  /// understands, so a newer format isn't silently misinterpreted.
+  pub fn check_version(&self) -> Result<(), UnsupportedVersion> {
+    if self.version != BLUEPRINT_VERSION {
+      return Err(UnsupportedVersion::Blueprint {
+        found: self.version,
+        expected: BLUEPRINT_VERSION,
+      });
+    }
+
+    if self.data.version != BLUEPRINT_DATA_VERSION {
+      return Err(UnsupportedVersion::Data {
+        found: self.data.version,
+        expected: BLUEPRINT_DATA_VERSION,
+      });
+    }
+
+    Ok(())
+  }
+
+  /// Checks that the indices within the blueprint refer to elements that
+  /// exist, that blocks have known types and fit in their frames, and that no
+  /// two blocks in a frame overlap, collecting every problem found.
+  pub fn validate(&self) -> Result<(), BlueprintError> {
+    let data = &self.data;
+    let mut issues = Vec::new();
+    let check = |referrer: String, list, index: usize, len| {
+      (index >= len).then_some(BlueprintIssue::IndexOutOfBounds {
+        referrer,
+        list,
+        index,
+        len,
+      })
+    };
+
+    for (i, pipe) in data.pipes.iter().enumerate() {
+      let len = data.components.len();
+      for index in [pipe.a_component, pipe.b_component] {
+        issues.extend(check(
+          format!("pipe {i}"),
+          "components",
+          index as usize,
+          len,
+        ));
+      }
+    }
+
+    for (index, block) in data.blocks.iter().enumerate() {
+      let overflow = block.frame_overflow();
+      if overflow != IVec3::ZERO {
+        issues.push(BlueprintIssue::BlockOutsideFrame { index, overflow });
+      }
+
+      if block.r#type >= BLOCK_TYPE_COUNT {
+        issues.push(BlueprintIssue::UnknownBlockType {
+          index,
+          type_id: block.r#type,
+        });
+      }
+    }
+
+    for (a, b) in data.overlaps() {
+      issues.push(BlueprintIssue::OverlappingBlocks {
+        indices: (a, b),
+        frame: data.blocks[a].frame(),
+      });
+    }
+
+    for (i, build) in data.composite_builds.iter().enumerate() {
+      issues.extend(check(
+        format!("composite build {i}"),
+        "components",
+        build.component as usize,
+        data.components.len(),
+      ));
+    }
+
+    if issues.is_empty() {
+      Ok(())
+    } else {
+      Err(BlueprintError::Validation(issues))
+    }
+  }
+
+  /// Builds a blueprint of unit cube blocks filling a volume of `dims` blocks
+  /// according to `fill`, with a frame for every frame the blocks touch.
+  ///
+  /// This is meant for tests and benchmarks that need a blueprint of a
+  /// controlled size without shipping large fixtures. `dims` is clamped to the
+  /// 128 frames along each axis that frame coordinates can reach from zero.
+  pub fn synthetic(dims: UVec3, fill: FillPattern) -> Self {
+    let frame_size = IVec3::splat(FRAME_SIZE as i32);
+    let max_dims = (i8::MAX as u32 + 1) * FRAME_SIZE as u32;
+    let dims = dims.min(UVec3::splat(max_dims));
+
+    let mut blocks = Vec::new();
+    let mut frames = HashSet::new();
+    for x in 0..dims.x {
+      for y in 0..dims.y {
+        for z in 0..dims.z {
+          let cell = UVec3::new(x, y, z);
+          if !fill.contains(cell, dims) {
+            continue;
+          }
+
+          let frame = cell.as_ivec3().div_euclid(frame_size);
+          let pos = cell.as_ivec3().rem_euclid(frame_size);
+          blocks.push(Block {
+            colors: (0, 0, 0, 0, 0, 0, 0),
+            extra: 0,
+            frame_x: frame.x as i8,
+            frame_y: frame.y as i8,
+            frame_z: frame.z as i8,
+            material: 0,
+            pos_x: pos.x as u8,
+            pos_y: pos.y as u8,
+            pos_z: pos.z as u8,
+            size_x: 0,
+            size_y: 0,
+            size_z: 0,
+            r#type: 0,
+          });
+          frames.insert(frame);
+        }
+      }
+    }
+
+    let mut frames = frames.into_iter().collect::<Vec<_>>();
+    frames.sort_by_key(|frame| (frame.x, frame.y, frame.z));
+
+    let zero = Coords {
+      x: 0.0,
+      y: 0.0,
+      z: 0.0,
+    };
+    let size = Coords {
+      x: dims.x as f64,
+      y: dims.y as f64,
+      z: dims.z as f64,
+    };
+
+    Self {
+      author: String::new(),
+      box_max: size,
+      box_min: zero,
+      box_size: size,
+      data: BlueprintData {
+        alias: "synthetic".into(),
+        blocks,
+        colors: Vec::new(),
+        components: Vec::new(),
+        composite_builds: Vec::new(),
+        doors: Vec::new(),
+        frames: frames
+          .into_iter()
+          .map(|frame| Frame {
+            beams: (0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0),
+            frame_x: frame.x as i8,
+            frame_y: frame.y as i8,
+            frame_z: frame.z as i8,
+          })
+          .collect(),
+        labels: Vec::new(),
+        pipes: Vec::new(),
+        symmetry_axis: 0,
+        symmetry_axis_offset: zero,
+        version: BLUEPRINT_DATA_VERSION,
+      },
+      datetime: String::new(),
+      mass: 0.0,
+      r#type: String::new(),
+      version: BLUEPRINT_VERSION,
+    }
+  }
+}
+
+/// How [`Blueprint::synthetic`] fills its volume with blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FillPattern {
+  /// Every cell holds a block.
+  #[default]
+  Solid,
+  /// Alternating cells hold a block, like a 3D checkerboard.
+  Checker,
+  /// Only the cells on the outside of the volume hold a block.
+  Shell,
+}
+
+impl FillPattern {
+  /// Whether `cell` of a volume of `dims` holds a block.
+  pub fn contains(self, cell: UVec3, dims: UVec3) -> bool {
+    match self {
+      Self::Solid => true,
+      Self::Checker => (cell.x + cell.y + cell.z).is_multiple_of(2),
+      Self::Shell => {
+        cell.cmpeq(UVec3::ZERO).any() || cell.cmpeq(dims - 1).any()
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use core::f32::consts::FRAC_PI_2;
+
+  use super::*;
+
+  #[test]
+  fn compute_mass_sums_volume_times_density() {
+    let mut blueprint =
+      Blueprint::synthetic(UVec3::new(2, 1, 1), FillPattern::Solid);
+    let blocks = &mut blueprint.data.blocks;
+    // A 2x3x1 block of material 0 and a 1x1x4 block of material 1.
+    (blocks[0].size_x, blocks[0].size_y) = (1, 2);
+    blocks[1].size_z = 3;
+    blocks[1].material = 1;
+
+    let mass = blueprint.data.compute_mass(&[2.0, 0.5]);
+    assert_eq!(mass, 6.0 * 2.0 + 4.0 * 0.5);
+    // Materials without a density count as one.
+    assert_eq!(blueprint.data.compute_mass(&[2.0]), 6.0 * 2.0 + 4.0);
+  }
+
+  #[test]
+  fn world_transform_centers_scaled_cube_in_block() {
+    let blueprint =
+      Blueprint::synthetic(UVec3::new(1, 1, 1), FillPattern::Solid);
+    let mut block = blueprint.data.blocks[0].clone();
+    // A 2x3x4 block at (1, 2, 3) in frame (1, 0, -1).
+    (block.frame_x, block.frame_y, block.frame_z) = (1, 0, -1);
+    (block.pos_x, block.pos_y, block.pos_z) = (1, 2, 3);
+    (block.size_x, block.size_y, block.size_z) = (1, 2, 3);
+
+    let transform = block.world_transform();
+    assert_eq!(transform.translation, Vec3::new(14.0, 3.5, -7.0));
+    assert_eq!(transform.scale, Vec3::new(2.0, 3.0, 4.0));
+    assert_eq!(transform.rotation, Quat::IDENTITY);
+  }
+
+  #[test]
+  fn check_version_refuses_other_versions() {
+    // Saved blueprints carry these, so changing them needs a migration.
+    assert_eq!((BLUEPRINT_VERSION, BLUEPRINT_DATA_VERSION), (1, 1));
+
+    let parse = |version: u8, data_version: u8| {
+      let json = format!(
+        concat!(
+          r#"{{"author":"","box_max":{{"x":1.0,"y":1.0,"z":1.0}},"#,
+          r#""box_min":{{"x":0.0,"y":0.0,"z":0.0}},"#,
+          r#""box_size":{{"x":1.0,"y":1.0,"z":1.0}},"#,
+          r#""data":{{"alias":"fixture","blocks":[],"colors":[],"#,
+          r#""components":[],"composite_builds":[],"doors":[],"frames":[],"#,
+          r#""labels":[],"pipes":[],"symmetry_axis":0,"#,
+          r#""symmetry_axis_offset":{{"x":0.0,"y":0.0,"z":0.0}},"#,
+          r#""version":{}}},"datetime":"","mass":0.0,"type":"","version":{}}}"#,
+        ),
+        data_version, version,
+      );
+      serde_json::from_str::<Blueprint>(&json).unwrap()
+    };
+
+    assert_eq!(parse(1, 1).check_version(), Ok(()));
+    let error = parse(2, 1).check_version().unwrap_err();
+    assert_eq!(
+      error,
+      UnsupportedVersion::Blueprint {
+        found: 2,
+        expected: 1
+      }
+    );
+    assert_eq!(
+      error.to_string(),
+      "blueprint version 2 is not supported, expected 1"
+    );
+    assert_eq!(
+      parse(1, 0).check_version(),
+      Err(UnsupportedVersion::Data {
+        found: 0,
+        expected: 1
+      })
+    );
+  }
+
+  #[test]
+  fn door_round_trips() {
+    let json = concat!(
+      r#"{"component":3,"kind":"hatch","locked":false,"#,
+      r#""orientation":{"w":1.0,"x":0.0,"y":0.0,"z":0.0},"#,
+      r#""position":{"x":1.5,"y":0.0,"z":-2.0},"#,
+      r#""size":{"x":2.0,"y":3.0,"z":1.0},"state":null,"#,
+      r#""zones":[1,2.5,{"a":null}]}"#,
+    );
+    let door: Door = serde_json::from_str(json).unwrap();
+    assert_eq!(door.component, Some(3));
+    assert_eq!(door.position.unwrap().to_vec3(), Vec3::new(1.5, 0.0, -2.0));
+    assert!(door.state.as_ref().is_some_and(Value::is_null));
+    assert_eq!(serde_json::to_string(&door).unwrap(), json);
+  }
+
+  #[test]
+  fn raycast_hits_nearest_block() {
+    let blueprint =
+      Blueprint::synthetic(UVec3::new(3, 1, 1), FillPattern::Solid);
+    let data = &blueprint.data;
+
+    // From beyond the far end, the last block of the row is hit first.
+    let hit = data.raycast(Vec3::new(5.0, 0.5, 0.5), Vec3::NEG_X, FRAME_SIZE);
+    assert_eq!(hit, Some((2, 2.0)));
+    // The direction doesn't have to be normalized.
+    let hit =
+      data.raycast(Vec3::new(1.5, 4.0, 0.5), Vec3::NEG_Y * 3.0, FRAME_SIZE);
+    assert_eq!(hit, Some((1, 3.0)));
+    // A ray starting inside a block hits it at once.
+    let hit = data.raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::X, FRAME_SIZE);
+    assert_eq!(hit, Some((0, 0.0)));
+    // A ray grazing the top faces still hits.
+    let hit = data.raycast(Vec3::new(-1.0, 1.0, 0.5), Vec3::X, FRAME_SIZE);
+    assert_eq!(hit, Some((0, 1.0)));
+    let miss = data.raycast(Vec3::new(-1.0, 2.0, 0.5), Vec3::X, FRAME_SIZE);
+    assert_eq!(miss, None);
+    assert_eq!(data.raycast(Vec3::ZERO, Vec3::ZERO, FRAME_SIZE), None);
+  }
+
+  #[test]
+  fn blocks_by_frame_groups_block_indices() {
+    let mut blueprint =
+      Blueprint::synthetic(UVec3::new(14, 1, 1), FillPattern::Solid);
+    let data = &mut blueprint.data;
+    // Move the last block into a frame that isn't in the frame list.
+    data.blocks[13].set_min(IVec3::new(0, -12, 0));
+
+    let by_frame = data.blocks_by_frame();
+    assert_eq!(by_frame.len(), 3);
+    assert_eq!(by_frame[&IVec3::ZERO], (0..12).collect::<Vec<_>>());
+    assert_eq!(by_frame[&IVec3::X], [12]);
+    assert_eq!(by_frame[&IVec3::NEG_Y], [13]);
+    assert_eq!(
+      data.frame_coords(),
+      HashSet::from_iter([IVec3::ZERO, IVec3::X]),
+    );
+  }
+
+  #[test]
+  fn world_to_frame_pos_inverts_block_placement() {
+    assert_eq!(
+      world_to_frame_pos(Vec3::new(13.5, 0.5, -0.5), FRAME_SIZE),
+      (IVec3::new(1, 0, -1), UVec3::new(1, 0, 11)),
+    );
+
+    // Rounding can put a point just below a frame on its far edge.
+    assert_eq!(
+      world_to_frame_pos(Vec3::new(-1e-7, 0.0, 0.0), FRAME_SIZE),
+      (IVec3::NEG_X, UVec3::new(11, 0, 0)),
+    );
+
+    let blueprint =
+      Blueprint::synthetic(UVec3::new(1, 1, 1), FillPattern::Solid);
+    let mut block = blueprint.data.blocks[0].clone();
+    for min in [IVec3::ZERO, IVec3::new(-25, 30, 7), IVec3::new(11, -1, 12)] {
+      block.set_min(min);
+      let pos =
+        UVec3::new(block.pos_x as u32, block.pos_y as u32, block.pos_z as u32);
+      assert_eq!(
+        world_to_frame_pos(block.world_transform().translation, FRAME_SIZE),
+        (block.frame(), pos),
+      );
+    }
+  }
+
+  #[test]
+  fn palette_colors_resolve_by_index() {
+    let mut blueprint =
+      Blueprint::synthetic(UVec3::new(1, 1, 1), FillPattern::Solid);
+    let data = &mut blueprint.data;
+    data.colors = serde_json::from_str(concat!(
+      r#"[0,{"b":30,"g":20,"metallic":0,"opacity":255,"r":10,"#,
+      r#""roughness":128}]"#,
+    ))
+    .unwrap();
+
+    let rgb = |color: Option<ColorMaterial>| color.map(|c| (c.r, c.g, c.b));
+    assert_eq!(rgb(data.color(0)), None);
+    assert_eq!(rgb(data.color(1)), Some((10, 20, 30)));
+    assert_eq!(rgb(data.color(2)), None);
+
+    data.blocks[0].colors.0 = 1;
+    assert_eq!(rgb(data.block_color(&data.blocks[0])), Some((10, 20, 30)));
+  }
+
+  #[test]
+  fn color_material_converts_to_standard_material() {
+    let color = ColorMaterial {
+      r: 255,
+      g: 0,
+      b: 51,
+      metallic: 255,
+      opacity: 255,
+      roughness: 0,
+    };
+    let material = StandardMaterial::from(color);
+    assert_eq!(material.base_color, Color::srgba_u8(255, 0, 51, 255));
+    assert_eq!(material.metallic, 1.0);
+    assert_eq!(material.perceptual_roughness, 0.0);
+    assert!(matches!(material.alpha_mode, AlphaMode::Opaque));
+
+    let translucent = ColorMaterial {
+      opacity: 128,
+      ..color
+    };
+    assert_eq!(translucent.to_color().alpha(), 128.0 / 255.0);
+    let material = StandardMaterial::from(&translucent);
+    assert!(matches!(material.alpha_mode, AlphaMode::Blend));
+  }
+
+  #[test]
+  fn coords_w_to_quat_reorders_and_normalizes() {
+    // A quarter turn about Y, as (w, x, y, z), scaled up.
+    let half = core::f64::consts::FRAC_1_SQRT_2 * 2.0;
+    let orientation = CoordsW {
+      w: half,
+      x: 0.0,
+      y: half,
+      z: 0.0,
+    };
+    let quat = orientation.to_quat();
+    assert!(quat.abs_diff_eq(Quat::from_rotation_y(FRAC_PI_2), 1e-6));
+    assert!((quat * Vec3::X).abs_diff_eq(Vec3::NEG_Z, 1e-6));
+
+    let zero = CoordsW {
+      w: 0.0,
+      x: 0.0,
+      y: 0.0,
+      z: 0.0,
+    };
+    assert_eq!(zero.to_quat(), Quat::IDENTITY);
+  }
+
+  #[test]
+  fn remove_component_renumbers_references() {
+    let component: Component = serde_json::from_str(concat!(
+      r#"{"colors":{},"data":{},"module":"","occupancies":[],"#,
+      r#""orientation":{"w":1.0,"x":0.0,"y":0.0,"z":0.0},"#,
+      r#""position":{"x":0.0,"y":0.0,"z":0.0},"type":"tank"}"#,
+    ))
+    .unwrap();
+    let pipe = |a_component, b_component| Pipe {
+      a_component,
+      a_port: String::new(),
+      b_component,
+      b_port: String::new(),
+      radius: 0.1,
+      segments: Vec::new(),
+      r#type: String::new(),
+    };
+    let mut blueprint =
+      Blueprint::synthetic(UVec3::new(1, 1, 1), FillPattern::Solid);
+    let data = &mut blueprint.data;
+    data.components = vec![component.clone(); 3];
+    data.pipes = vec![pipe(0, 2), pipe(1, 2)];
+    data.composite_builds = [0, 1, 2]
+      .map(|component| CompositeBuild {
+        component,
+        slave_build_id: component,
+      })
+      .into();
+
+    assert!(data.remove_component(1).is_ok());
+    assert_eq!(data.components.len(), 2);
+    let pipes = data.pipes.iter().map(|p| (p.a_component, p.b_component));
+    assert_eq!(pipes.collect::<Vec<_>>(), [(0, 1)]);
+    let builds = data
+      .composite_builds
+      .iter()
+      .map(|build| (build.component, build.slave_build_id));
+    assert_eq!(builds.collect::<Vec<_>>(), [(0, 0), (1, 2)]);
+
+    assert_eq!(
+      data.remove_component(2).err(),
+      Some(RemoveComponentError::OutOfRange { index: 2, len: 2 }),
+    );
+    data.components = vec![component; 300];
+    assert_eq!(
+      data.remove_component(256).err(),
+      Some(RemoveComponentError::Unreferenceable(256)),
+    );
+    assert_eq!(data.components.len(), 300);
+    assert!(data.remove_component(255).is_ok());
+  }
+
+  #[test]
+  fn synthetic_blueprints_validate() {
+    for fill in [FillPattern::Solid, FillPattern::Checker, FillPattern::Shell] {
+      let blueprint = Blueprint::synthetic(UVec3::new(13, 5, 25), fill);
+      blueprint.validate().unwrap();
+    }
+
+    // Frames past 127 can't be stored, so the volume is clamped.
+    let blueprint =
+      Blueprint::synthetic(UVec3::new(2000, 1, 1), FillPattern::Solid);
+    blueprint.validate().unwrap();
+    assert_eq!(blueprint.data.blocks.len(), 128 * 12);
+    assert_eq!(blueprint.box_size.x, 128.0 * 12.0);
+    let frames = blueprint.data.frames.iter().map(|frame| frame.frame_x);
+    assert_eq!(frames.max(), Some(i8::MAX));
+  }
+}
@@ -0,0 +1,400 @@
+//! The orbiting editor camera.
+
+use core::{f32::consts::FRAC_PI_2, ops::Range};
+
+use bevy::{
+  camera::ScalingMode,
+  input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll},
+  prelude::*,
+};
+
+use crate::{BlockData, Selected, selection_bounds};
+
+/// How long focusing the camera takes, in seconds.
+const FOCUS_DURATION: f32 = 0.3;
+
+#[derive(Default)]
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<CameraSettings>()
+      .init_resource::<CameraBindings>()
+      .init_resource::<CameraFocus>()
+      .add_systems(Startup, spawn_camera)
+      .add_systems(
+        Update,
+        (
+          toggle_turntable,
+          focus_selection,
+          animate_focus,
+          view_presets,
+          reset_camera,
+          orbit,
+          apply_orthographic_zoom,
+        )
+          .chain(),
+      );
+  }
+}
+
+#[derive(Debug, Resource)]
+pub struct CameraSettings {
+  pub orbit_distance: f32,
+  /// Clamp orbit distance to this range
+  pub zoom_range: Range<f32>,
+  pub pitch_speed: f32,
+  // Clamp pitch to this range
+  pub pitch_range: Range<f32>,
+  pub yaw_speed: f32,
+  /// How far the target moves per pixel of mouse motion while panning, per
+  /// unit of orbit distance, so that the view follows the cursor at any zoom.
+  pub pan_speed: f32,
+  pub target: Vec3,
+  /// Whether the camera slowly orbits the target by itself, to present the
+  /// blueprint. Orbiting by hand turns this off.
+  pub turntable: bool,
+  /// How fast the turntable turns, in radians per second.
+  pub turntable_speed: f32,
+  /// Whether the turntable turns clockwise, looking down from above.
+  pub turntable_clockwise: bool,
+  /// Where [`reset_camera`] puts the camera.
+  pub default_view: CameraView,
+}
+
+impl Default for CameraSettings {
+  fn default() -> Self {
+    const SPEED: f32 = 0.02;
+
+    // Limiting pitch stops some unexpected rotation past 90° up or down.
+    let pitch_limit = FRAC_PI_2 - 0.01;
+    Self {
+      // These values are completely arbitrary, chosen because they seem to produce
+      // "sensible" results for this example. Adjust as required.
+      orbit_distance: 10.0,
+      zoom_range: 1.0..5000.0,
+      pitch_speed: SPEED,
+      pitch_range: -pitch_limit..pitch_limit,
+      yaw_speed: SPEED * 0.5,
+      pan_speed: 0.01,
+      target: Vec3::ZERO,
+      turntable: false,
+      turntable_speed: 0.3,
+      turntable_clockwise: false,
+      default_view: CameraView::default(),
+    }
+  }
+}
+
+/// A vantage point of the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraView {
+  /// The point to orbit around, or the center of the box enclosing every
+  /// block if [`None`].
+  pub target: Option<Vec3>,
+  pub orbit_distance: f32,
+  pub rotation: Quat,
+}
+
+impl Default for CameraView {
+  fn default() -> Self {
+    Self {
+      target: None,
+      orbit_distance: 10.0,
+      // Looking down at the target diagonally, as the camera starts.
+      rotation: Transform::from_translation(Vec3::ONE)
+        .looking_at(Vec3::ZERO, Dir3::Y)
+        .rotation,
+    }
+  }
+}
+
+/// The inputs controlling the camera.
+#[derive(Debug, Clone, Resource)]
+pub struct CameraBindings {
+  /// Held while moving the mouse to orbit around the target.
+  pub orbit: MouseButton,
+  /// Held together with [`Self::orbit`] to pan instead of orbiting.
+  pub pan_modifiers: Vec<KeyCode>,
+}
+
+impl Default for CameraBindings {
+  fn default() -> Self {
+    Self {
+      orbit: MouseButton::Middle,
+      pan_modifiers: vec![KeyCode::ShiftLeft, KeyCode::ShiftRight],
+    }
+  }
+}
+
+/// A transition of the camera's target and orbit distance, started by
+/// [`focus_selection`].
+#[derive(Debug, Clone, Copy, PartialEq, Default, Resource)]
+pub struct CameraFocus {
+  /// The target and orbit distance when the transition started, and the ones
+  /// it ends at, while there is one.
+  pub transition: Option<((Vec3, f32), (Vec3, f32))>,
+  /// How long the transition has run, in seconds.
+  pub elapsed: f32,
+}
+
+/// Starts moving the camera to frame the selected blocks, or every block if
+/// none are selected, aiming at their centroid from far enough away to see
+/// the box enclosing them.
+pub fn focus_selection(
+  keycode: Res<ButtonInput<KeyCode>>,
+  camera: Single<&Projection, With<Camera3d>>,
+  camera_settings: Res<CameraSettings>,
+  blocks: Query<(&BlockData, &Transform, Has<Selected>)>,
+  mut camera_focus: ResMut<CameraFocus>,
+) {
+  // TODO: Make controls configurable.
+  if !keycode.just_pressed(KeyCode::KeyF) {
+    return;
+  }
+
+  let any_selected = blocks.iter().any(|(.., selected)| selected);
+  let focused = || {
+    blocks
+      .iter()
+      .filter(move |(.., selected)| *selected || !any_selected)
+  };
+
+  let Some((min, max)) =
+    selection_bounds(focused().map(|(block, ..)| &block.0))
+  else {
+    return;
+  };
+  let count = focused().count() as f32;
+  let target = focused()
+    .map(|(_, transform, _)| transform.translation)
+    .sum::<Vec3>()
+    / count;
+
+  // The centroid needn't be the center of the box, so fit the corner
+  // furthest from it.
+  let (min, max) = (min.as_vec3(), max.as_vec3());
+  let radius = [min, max]
+    .into_iter()
+    .flat_map(|x| [min, max].map(|y| (x.x, y.y)))
+    .flat_map(|(x, y)| [min.z, max.z].map(|z| Vec3::new(x, y, z)))
+    .map(|corner| corner.distance(target))
+    .fold(0.0, f32::max);
+  let fov = match *camera {
+    Projection::Perspective(perspective) => perspective.fov,
+    _ => PerspectiveProjection::default().fov,
+  };
+  let distance = (radius / (fov * 0.5).sin()).max(1.0);
+
+  *camera_focus = CameraFocus {
+    transition: Some((
+      (camera_settings.target, camera_settings.orbit_distance),
+      (target, distance),
+    )),
+    elapsed: 0.0,
+  };
+}
+
+/// Moves the camera's target and orbit distance along the [`CameraFocus`]
+/// transition, easing in and out. Orbiting by hand cancels it.
+pub fn animate_focus(
+  mut camera_focus: ResMut<CameraFocus>,
+  mut camera_settings: ResMut<CameraSettings>,
+  mouse_buttons: Res<ButtonInput<MouseButton>>,
+  camera_bindings: Res<CameraBindings>,
+  time: Res<Time>,
+) {
+  let Some(((from_target, from_distance), (to_target, to_distance))) =
+    camera_focus.transition
+  else {
+    return;
+  };
+
+  if mouse_buttons.pressed(camera_bindings.orbit) {
+    camera_focus.transition = None;
+    return;
+  }
+
+  camera_focus.elapsed += time.delta_secs();
+  let t = (camera_focus.elapsed / FOCUS_DURATION).min(1.0);
+  let eased = t * t * (3.0 - 2.0 * t);
+  camera_settings.target = from_target.lerp(to_target, eased);
+  camera_settings.orbit_distance = from_distance.lerp(to_distance, eased);
+
+  if t >= 1.0 {
+    camera_focus.transition = None;
+  }
+}
+
+/// The yaw and pitch of each axis-aligned view, looking down, from the front
+/// along -Z, and from the side along -X.
+///
+/// Straight down is just inside the default pitch range, so that orbiting
+/// from it doesn't jump.
+const VIEW_PRESETS: [(KeyCode, f32, f32); 3] = [
+  (KeyCode::Numpad7, 0.0, -(FRAC_PI_2 - 0.01)),
+  (KeyCode::Numpad1, 0.0, 0.0),
+  (KeyCode::Numpad3, FRAC_PI_2, 0.0),
+];
+
+/// An orthographic projection showing as much at the target as the default
+/// perspective does from `orbit_distance` away.
+fn orthographic(orbit_distance: f32) -> OrthographicProjection {
+  let fov = PerspectiveProjection::default().fov;
+  OrthographicProjection {
+    scaling_mode: ScalingMode::FixedVertical {
+      viewport_height: 2.0 * orbit_distance * (fov * 0.5).tan(),
+    },
+    ..OrthographicProjection::default_3d()
+  }
+}
+
+/// Snaps the camera to look down, from the front or from the side with an
+/// orthographic projection, or toggles back to perspective.
+pub fn view_presets(
+  keycode: Res<ButtonInput<KeyCode>>,
+  camera: Single<(&mut Transform, &mut Projection), With<Camera3d>>,
+  mut camera_settings: ResMut<CameraSettings>,
+) {
+  let (mut transform, mut projection) = camera.into_inner();
+  let is_orthographic = matches!(*projection, Projection::Orthographic(_));
+
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::Numpad5) {
+    *projection = if is_orthographic {
+      Projection::Perspective(PerspectiveProjection::default())
+    } else {
+      Projection::Orthographic(orthographic(camera_settings.orbit_distance))
+    };
+    return;
+  }
+
+  let Some((_, yaw, pitch)) = VIEW_PRESETS
+    .into_iter()
+    .find(|(key, ..)| keycode.just_pressed(*key))
+  else {
+    return;
+  };
+
+  camera_settings.turntable = false;
+  transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+  if !is_orthographic {
+    *projection =
+      Projection::Orthographic(orthographic(camera_settings.orbit_distance));
+  }
+}
+
+/// Zooms an orthographic camera by scaling its view with the orbit distance,
+/// as moving closer doesn't make anything larger.
+pub fn apply_orthographic_zoom(
+  mut projection: Single<&mut Projection, With<Camera3d>>,
+  camera_settings: Res<CameraSettings>,
+) {
+  if let Projection::Orthographic(projection) = &mut **projection {
+    projection.scaling_mode =
+      orthographic(camera_settings.orbit_distance).scaling_mode;
+  }
+}
+
+/// Puts the camera back at the [`CameraSettings::default_view`] at once,
+/// stopping the turntable and any focus transition.
+pub fn reset_camera(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut camera: Single<&mut Transform, With<Camera3d>>,
+  mut camera_settings: ResMut<CameraSettings>,
+  mut camera_focus: ResMut<CameraFocus>,
+  blocks: Query<&BlockData>,
+) {
+  // TODO: Make controls configurable.
+  if !keycode.just_pressed(KeyCode::Home) {
+    return;
+  }
+
+  let view = camera_settings.default_view;
+  camera_settings.target = view.target.unwrap_or_else(|| {
+    selection_bounds(blocks.iter().map(|block| &block.0))
+      .map_or(Vec3::ZERO, |(min, max)| (min + max).as_vec3() * 0.5)
+  });
+  camera_settings.orbit_distance = view.orbit_distance;
+  camera_settings.turntable = false;
+  camera.rotation = view.rotation;
+  camera_focus.transition = None;
+}
+
+pub fn spawn_camera(mut commands: Commands) {
+  commands.spawn((
+    Camera3d::default(),
+    Transform::from_translation(Vec3::ONE * 10.0)
+      .looking_at(Vec3::ZERO, Dir3::Y),
+    MeshPickingCamera,
+  ));
+}
+
+pub fn toggle_turntable(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut camera_settings: ResMut<CameraSettings>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::KeyT) {
+    camera_settings.turntable = !camera_settings.turntable;
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn orbit(
+  mut camera: Single<&mut Transform, With<Camera3d>>,
+  mut camera_settings: ResMut<CameraSettings>,
+  mouse_motion: Res<AccumulatedMouseMotion>,
+  mouse_buttons: Res<ButtonInput<MouseButton>>,
+  mouse_scroll: Res<AccumulatedMouseScroll>,
+  key_input: Res<ButtonInput<KeyCode>>,
+  camera_bindings: Res<CameraBindings>,
+  time: Res<Time>,
+) {
+  let zoom_delta = mouse_scroll.delta;
+  // A large scroll in one frame mustn't flip the camera through the target.
+  let zoom = (1.0 - time.delta_secs() * zoom_delta.y * 15.0).max(0.1);
+  camera_settings.orbit_distance = (camera_settings.orbit_distance * zoom)
+    .clamp(
+      camera_settings.zoom_range.start,
+      camera_settings.zoom_range.end,
+    );
+
+  if mouse_buttons.pressed(camera_bindings.orbit) {
+    // Orbiting by hand takes over from the turntable until it is turned back
+    // on.
+    camera_settings.turntable = false;
+    let delta = mouse_motion.delta;
+
+    if key_input.any_pressed(camera_bindings.pan_modifiers.iter().copied()) {
+      // Move in the view plane only, whatever the pitch.
+      let speed = camera_settings.pan_speed * camera_settings.orbit_distance;
+      let x = -camera.right() * delta.x * speed;
+      let y = camera.up() * delta.y * speed;
+      camera_settings.target += x + y;
+    } else {
+      let delta_pitch = -delta.y * camera_settings.pitch_speed;
+      let delta_yaw = -delta.x * camera_settings.yaw_speed;
+
+      let (yaw, pitch, _) = camera.rotation.to_euler(EulerRot::YXZ);
+
+      let pitch = (pitch + delta_pitch).clamp(
+        camera_settings.pitch_range.start,
+        camera_settings.pitch_range.end,
+      );
+      let yaw = yaw + delta_yaw;
+      camera.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+    }
+  } else if camera_settings.turntable {
+    let speed = if camera_settings.turntable_clockwise {
+      -camera_settings.turntable_speed
+    } else {
+      camera_settings.turntable_speed
+    };
+    camera.rotate_y(speed * time.delta_secs());
+  }
+
+  camera.translation =
+    camera_settings.target - camera.forward() * camera_settings.orbit_distance;
+}
@@ -0,0 +1,117 @@
+//! Drawing the cells the blueprint's components fill.
+
+use bevy::prelude::*;
+
+use crate::{
+  PickCategory, PickingFilter,
+  blueprint::{Blueprint, BlueprintState, LoadedBlueprint},
+  select::SelectionChanged,
+};
+
+#[derive(Default)]
+pub struct ComponentPlugin;
+
+impl Plugin for ComponentPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<InspectedComponent>()
+      .add_systems(OnEnter(BlueprintState::Loaded), spawn_components)
+      .add_systems(OnExit(BlueprintState::Loaded), clear_inspected_component)
+      .add_systems(
+        Update,
+        clear_inspected_component.run_if(on_message::<SelectionChanged>),
+      );
+  }
+}
+
+/// Marks an entity drawing the cells of a component, with the index of the
+/// component in [`BlueprintData::components`].
+///
+/// [`BlueprintData::components`]: crate::blueprint::BlueprintData::components
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct ComponentRef(pub usize);
+
+/// The index of the component last clicked, which the inspector shows until
+/// the selection changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct InspectedComponent(pub Option<usize>);
+
+/// Spawns a translucent box for every occupancy of every component in the
+/// loaded blueprint, in place of the component's model.
+///
+/// The boxes can be picked as [`PickCategory::Component`], and clicking one
+/// inspects the component it belongs to.
+pub fn spawn_components(
+  mut commands: Commands,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  picking_filter: Res<PickingFilter>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  if blueprint.data.components.is_empty() {
+    return;
+  }
+
+  let mesh = meshes.add(Cuboid::from_length(1.0));
+  let material = materials.add(StandardMaterial {
+    base_color: Color::srgba(0.2, 0.5, 0.9, 0.5),
+    alpha_mode: AlphaMode::Blend,
+    ..Default::default()
+  });
+
+  for (index, component) in blueprint.data.components.iter().enumerate() {
+    for occupancy in component.occupancies.iter() {
+      let (min, max) = occupancy.aabb();
+      commands
+        .spawn((
+          DespawnOnExit(BlueprintState::Loaded),
+          ComponentRef(index),
+          Mesh3d(mesh.clone()),
+          MeshMaterial3d(material.clone()),
+          Transform::from_translation((min + max) * 0.5).with_scale(max - min),
+          PickCategory::Component,
+          picking_filter.pickable(PickCategory::Component),
+        ))
+        .observe(inspect_component);
+    }
+  }
+}
+
+/// Logs the module, type and alias of the component whose box was clicked,
+/// and makes it the [`InspectedComponent`].
+pub fn inspect_component(
+  event: On<Pointer<Click>>,
+  components: Query<&ComponentRef>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut inspected: ResMut<InspectedComponent>,
+) {
+  let Ok(ComponentRef(index)) = components.get(event.entity) else {
+    return;
+  };
+  let Some(component) = blueprints
+    .get(blueprint.id())
+    .and_then(|blueprint| blueprint.data.components.get(*index))
+  else {
+    return;
+  };
+
+  info!(
+    "Component {index} is a {} from module {}, aliased {}",
+    component.r#type,
+    component.module,
+    component.alias.as_deref().unwrap_or("nothing")
+  );
+  inspected.0 = Some(*index);
+}
+
+/// Stops inspecting the [`InspectedComponent`], so that the inspector shows
+/// the selected blocks instead.
+pub fn clear_inspected_component(mut inspected: ResMut<InspectedComponent>) {
+  inspected.set_if_neq(InspectedComponent(None));
+}
@@ -0,0 +1,66 @@
+//! Drawing the blueprint's doors.
+
+use bevy::prelude::*;
+
+use crate::blueprint::{Blueprint, BlueprintState, LoadedBlueprint};
+
+#[derive(Default)]
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems(OnEnter(BlueprintState::Loaded), spawn_doors);
+  }
+}
+
+/// Marks an entity drawing a door, with the index of the door in
+/// [`BlueprintData::doors`].
+///
+/// [`BlueprintData::doors`]: crate::blueprint::BlueprintData::doors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct DoorRef(pub usize);
+
+/// Spawns a translucent box for every door in the loaded blueprint that has a
+/// position, as large as the door or a single block if it has no size.
+///
+/// Doors can't be picked, so that they don't get in the way of selecting
+/// blocks.
+pub fn spawn_doors(
+  mut commands: Commands,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  if blueprint.data.doors.is_empty() {
+    return;
+  }
+
+  let mesh = meshes.add(Cuboid::from_length(1.0));
+  let material = materials.add(StandardMaterial {
+    base_color: Color::srgba(0.6, 0.4, 0.2, 0.6),
+    alpha_mode: AlphaMode::Blend,
+    ..Default::default()
+  });
+
+  for (index, door) in blueprint.data.doors.iter().enumerate() {
+    let Some(transform) = door.transform() else {
+      warn!("Door {index} has no position, so it is not drawn");
+      continue;
+    };
+    let size = door.size.map_or(Vec3::ONE, |size| size.to_vec3());
+
+    commands.spawn((
+      DespawnOnExit(BlueprintState::Loaded),
+      DoorRef(index),
+      Mesh3d(mesh.clone()),
+      MeshMaterial3d(material.clone()),
+      transform.with_scale(size),
+      Pickable::IGNORE,
+    ));
+  }
+}
@@ -0,0 +1,456 @@
+//! The editor as a whole, combining the other plugins of the crate.
+
+use bevy::{
+  input::{ButtonState, keyboard::KeyboardInput},
+  pbr::wireframe::{WireframeConfig, WireframePlugin},
+  prelude::*,
+};
+use bevy_egui::EguiPlugin;
+use bevy_obj::ObjPlugin;
+
+use crate::{
+  BlockData, BlockMaterials, CommonAssets, EguiKeyboardFocus, FRAME_SIZE,
+  FallbackBlockTypes, Locked, PickingFilter, Selected, SelectionControls,
+  action::{
+    ActionMessage, ActionPlugin, BoxedAction, CombinedAction,
+    DeleteBlockAction, FlipAction, InsertBlocksAction, LockAction,
+    SelectionAction, TranslateAction, consume_actions_messages,
+  },
+  apply_picking_filter,
+  blueprint::{
+    Block, Blueprint, BlueprintPlugin, BlueprintState, LoadedBlueprint,
+  },
+  camera::CameraSettings,
+  component::ComponentPlugin,
+  door::DoorPlugin,
+  export::ExportPlugin,
+  label::LabelPlugin,
+  no_egui_keyboard_focus,
+  overlay::OverlayPlugin,
+  pipe::PipePlugin,
+  place::{CursorCell, EditorMode, PlacePlugin},
+  replace_missing_block_meshes, report_block_types, report_composite_builds,
+  save::{SavePlugin, WatchedFile},
+  select::SelectPlugin,
+  selection_bounds, setup_blueprint,
+  shape::GridAxis,
+  symmetry::{Symmetry, SymmetryPlugin},
+  sync_block_materials, sync_blocks,
+  ui::UiPlugin,
+  update_egui_keyboard_focus,
+  view::ViewPlugin,
+};
+
+/// Adds everything the editor needs on top of [`DefaultPlugins`], except for
+/// the camera, which [`CameraPlugin`] provides.
+///
+/// [`CameraPlugin`]: crate::camera::CameraPlugin
+#[derive(Default)]
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_plugins((MeshPickingPlugin, WireframePlugin::default()))
+      .add_plugins(EguiPlugin::default())
+      .add_plugins(ObjPlugin)
+      .add_plugins((
+        ActionPlugin,
+        BlueprintPlugin,
+        ComponentPlugin,
+        DoorPlugin,
+        ExportPlugin,
+        LabelPlugin,
+        OverlayPlugin,
+        PipePlugin,
+        PlacePlugin,
+        SavePlugin,
+        SelectPlugin,
+        SymmetryPlugin,
+        UiPlugin,
+        ViewPlugin,
+      ))
+      .insert_resource(MeshPickingSettings {
+        require_markers: true,
+        ..Default::default()
+      })
+      .insert_resource(WireframeConfig {
+        global: false,
+        ..Default::default()
+      })
+      .insert_resource(GlobalAmbientLight {
+        brightness: 500.0,
+        ..Default::default()
+      })
+      .init_resource::<CommonAssets>()
+      .init_resource::<PickingFilter>()
+      .init_resource::<SelectionControls>()
+      .init_resource::<BlockMaterials>()
+      .init_resource::<FallbackBlockTypes>()
+      .init_resource::<BlockClipboard>()
+      .init_resource::<EguiKeyboardFocus>()
+      .add_systems(Startup, setup_scene)
+      .add_systems(PreUpdate, update_egui_keyboard_focus)
+      .add_systems(
+        OnEnter(BlueprintState::Loaded),
+        (setup_blueprint, report_block_types, report_composite_builds),
+      )
+      .add_systems(
+        Update,
+        (
+          (
+            undo_redo,
+            delete_selection,
+            flip_selection,
+            clone_selection,
+            copy_selection,
+            paste_clipboard,
+            nudge_selection,
+            lock_selection,
+            reload_blueprint,
+            switch_blueprint,
+          )
+            .run_if(no_egui_keyboard_focus),
+          apply_picking_filter,
+          replace_missing_block_meshes,
+        ),
+      )
+      .add_systems(
+        PostUpdate,
+        (
+          sync_blocks.before(TransformSystems::Propagate),
+          sync_block_materials,
+        )
+          .after(consume_actions_messages),
+      );
+  }
+}
+
+pub fn setup_scene(mut commands: Commands) {
+  commands.spawn((
+    DirectionalLight {
+      illuminance: 10000.0,
+      shadows_enabled: true,
+      ..Default::default()
+    },
+    Transform::from_translation(Vec3::ONE * 10.0)
+      .looking_at(Vec3::ZERO, Dir3::Y),
+  ));
+}
+
+pub fn undo_redo(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.pressed(KeyCode::ControlLeft) {
+    // TODO: Make controls configurable.
+    if keycode.just_pressed(KeyCode::KeyZ) {
+      // TODO: Make controls configurable.
+      messages.write(if keycode.pressed(KeyCode::ShiftLeft) {
+        ActionMessage::Redo
+      } else {
+        ActionMessage::Undo
+      });
+    }
+
+    // TODO: Make controls configurable.
+    if keycode.just_pressed(KeyCode::KeyY) {
+      messages.write(ActionMessage::Redo);
+    }
+  }
+}
+
+/// Deletes the selected blocks, along with their mirrored counterparts while
+/// [`Symmetry`] is enabled.
+pub fn delete_selection(
+  keycode: Res<ButtonInput<KeyCode>>,
+  query: Query<(Entity, &BlockData), With<Selected>>,
+  blocks: Query<(Entity, &BlockData), Without<Locked>>,
+  symmetry: Res<Symmetry>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  if !(keycode.just_pressed(KeyCode::Delete)
+    || keycode.just_pressed(KeyCode::Backspace))
+    || query.is_empty()
+  {
+    return;
+  }
+
+  let mut entities = query.iter().map(|(entity, _)| entity).collect::<Vec<_>>();
+  if let Some(plane) = symmetry.active_plane(blueprints.get(blueprint.id())) {
+    for (_, block) in query.iter() {
+      let mirrored = plane.mirror(block);
+      let counterpart = blocks.iter().find(|(_, other)| {
+        other.min() == mirrored.min() && other.size() == mirrored.size()
+      });
+      if let Some((entity, _)) = counterpart
+        && !entities.contains(&entity)
+      {
+        entities.push(entity);
+      }
+    }
+  }
+
+  messages.write(ActionMessage::Push(Box::new(DeleteBlockAction::new(
+    entities,
+  ))));
+}
+
+pub fn flip_selection(
+  keycode: Res<ButtonInput<KeyCode>>,
+  query: Query<Entity, With<Selected>>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  if !keycode.pressed(KeyCode::AltLeft) || query.is_empty() {
+    return;
+  }
+
+  // TODO: Make controls configurable.
+  let axis = if keycode.just_pressed(KeyCode::KeyX) {
+    GridAxis::X
+  } else if keycode.just_pressed(KeyCode::KeyY) {
+    GridAxis::Y
+  } else if keycode.just_pressed(KeyCode::KeyZ) {
+    GridAxis::Z
+  } else {
+    return;
+  };
+
+  messages.write(ActionMessage::Push(Box::new(FlipAction {
+    entities: query.iter().collect(),
+    axis,
+  })));
+}
+
+/// Clones the selected blocks next to themselves along X, so that the copies
+/// don't overlap them.
+pub fn clone_selection(
+  keycode: Res<ButtonInput<KeyCode>>,
+  query: Query<&BlockData, With<Selected>>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  if !(keycode.pressed(KeyCode::ControlLeft)
+    && keycode.just_pressed(KeyCode::KeyD))
+  {
+    return;
+  }
+
+  let Some((min, max)) = selection_bounds(query.iter().map(|block| &block.0))
+  else {
+    return;
+  };
+
+  messages.write(ActionMessage::Push(Box::new(
+    InsertBlocksAction::clone_blocks(
+      query.iter().map(|block| block.0.clone()).collect(),
+      IVec3::X * (max - min).x,
+    ),
+  )));
+}
+
+/// The blocks last copied, positioned relative to the middle of the box
+/// enclosing them.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct BlockClipboard {
+  pub blocks: Vec<Block>,
+}
+
+/// Copies the selected blocks into the [`BlockClipboard`], replacing what it
+/// held, unless nothing is selected.
+pub fn copy_selection(
+  keycode: Res<ButtonInput<KeyCode>>,
+  query: Query<&BlockData, With<Selected>>,
+  mut clipboard: ResMut<BlockClipboard>,
+) {
+  // TODO: Make controls configurable.
+  if !(keycode.pressed(KeyCode::ControlLeft)
+    && keycode.just_pressed(KeyCode::KeyC))
+  {
+    return;
+  }
+
+  let Some((min, max)) = selection_bounds(query.iter().map(|block| &block.0))
+  else {
+    return;
+  };
+
+  let center = (min + max) / 2;
+  clipboard.blocks = query
+    .iter()
+    .map(|block| {
+      let mut block = block.0.clone();
+      let min = block.min() - center;
+      block.set_min(min);
+      block
+    })
+    .collect();
+}
+
+/// Pastes the [`BlockClipboard`] around the cell under the cursor in place
+/// mode, or around the camera's target otherwise.
+pub fn paste_clipboard(
+  keycode: Res<ButtonInput<KeyCode>>,
+  clipboard: Res<BlockClipboard>,
+  mode: Res<State<EditorMode>>,
+  cursor_cell: Res<CursorCell>,
+  camera_settings: Option<Res<CameraSettings>>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  if !(keycode.pressed(KeyCode::ControlLeft)
+    && keycode.just_pressed(KeyCode::KeyV))
+    || clipboard.blocks.is_empty()
+  {
+    return;
+  }
+
+  // The cursor cell is only kept up to date in place mode.
+  let origin = cursor_cell
+    .cell
+    .filter(|_| *mode.get() == EditorMode::Place)
+    .or_else(|| {
+      camera_settings.map(|settings| settings.target.floor().as_ivec3())
+    })
+    .unwrap_or(IVec3::ZERO);
+
+  messages.write(ActionMessage::Push(Box::new(InsertBlocksAction::paste(
+    clipboard.blocks.clone(),
+    origin,
+  ))));
+}
+
+/// Returns the grid direction along X or Z closest to `direction`, or [`None`]
+/// if it is vertical.
+fn horizontal_grid_direction(direction: Vec3) -> Option<IVec3> {
+  let direction = direction.with_y(0.0);
+  if direction.length_squared() < 1e-6 {
+    None
+  } else if direction.x.abs() > direction.z.abs() {
+    Some(IVec3::X * direction.x.signum() as i32)
+  } else {
+    Some(IVec3::Z * direction.z.signum() as i32)
+  }
+}
+
+/// Moves the selected blocks one block at a time with the arrow keys, along
+/// whichever grid axes are closest to the camera's forward and right, and up
+/// or down with Page Up and Page Down. Holding Shift moves them a whole frame
+/// instead.
+///
+/// Key repeats move them again, so holding a key keeps moving the blocks, and
+/// the moves are merged into one action as long as they come quickly enough.
+pub fn nudge_selection(
+  mut keyboard_input: MessageReader<KeyboardInput>,
+  keycode: Res<ButtonInput<KeyCode>>,
+  camera: Single<&GlobalTransform, With<Camera3d>>,
+  selected: Query<Entity, With<Selected>>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  let forward = horizontal_grid_direction(*camera.forward())
+    // Looking straight down, the top of the view is forward.
+    .or_else(|| horizontal_grid_direction(*camera.up()))
+    .unwrap_or(IVec3::NEG_Z);
+  let right = horizontal_grid_direction(*camera.right()).unwrap_or(IVec3::X);
+
+  // TODO: Make controls configurable.
+  let distance = if keycode.pressed(KeyCode::ShiftLeft) {
+    FRAME_SIZE as i32
+  } else {
+    1
+  };
+
+  for input in keyboard_input.read() {
+    if input.state != ButtonState::Pressed {
+      continue;
+    }
+
+    // TODO: Make controls configurable.
+    let direction = match input.key_code {
+      KeyCode::ArrowUp => forward,
+      KeyCode::ArrowDown => -forward,
+      KeyCode::ArrowRight => right,
+      KeyCode::ArrowLeft => -right,
+      KeyCode::PageUp => IVec3::Y,
+      KeyCode::PageDown => IVec3::NEG_Y,
+      _ => continue,
+    };
+
+    if selected.is_empty() {
+      continue;
+    }
+
+    messages.write(ActionMessage::Push(Box::new(TranslateAction {
+      entities: selected.iter().collect(),
+      delta: direction * distance,
+    })));
+  }
+}
+
+pub fn lock_selection(
+  keycode: Res<ButtonInput<KeyCode>>,
+  selected: Query<Entity, With<Selected>>,
+  locked: Query<Entity, With<Locked>>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  if !keycode.just_pressed(KeyCode::KeyL) {
+    return;
+  }
+
+  // TODO: Make controls configurable.
+  if keycode.pressed(KeyCode::ShiftLeft) {
+    if !locked.is_empty() {
+      let action = LockAction(locked.iter().collect());
+      messages.write(ActionMessage::Push(Box::new(action)));
+    }
+  } else if !selected.is_empty() {
+    // Locked blocks can't be selected, so deselect them before locking them.
+    messages.write(ActionMessage::Push(Box::new(CombinedAction::from_iter(
+      selected
+        .iter()
+        .map(|entity| Box::new(SelectionAction(entity)) as BoxedAction)
+        .chain(core::iter::once(
+          Box::new(LockAction(selected.iter().collect())) as _,
+        )),
+    ))));
+  }
+}
+
+pub fn reload_blueprint(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut messages: MessageWriter<ActionMessage>,
+  mut watched_file: ResMut<WatchedFile>,
+  asset_server: Res<AssetServer>,
+  blueprint: Res<LoadedBlueprint>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.pressed(KeyCode::ControlLeft)
+    && keycode.just_pressed(KeyCode::KeyR)
+  {
+    messages.write(ActionMessage::Clear);
+    watched_file.outdated = false;
+    if let Some(path) = blueprint.path() {
+      asset_server.reload(path);
+    }
+  }
+}
+
+pub fn switch_blueprint(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut messages: MessageWriter<ActionMessage>,
+  mut blueprint: ResMut<LoadedBlueprint>,
+  mut blueprint_state: ResMut<NextState<BlueprintState>>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::Tab) && blueprint.handles().len() > 1 {
+    messages.write(ActionMessage::Clear);
+    blueprint.cycle();
+    blueprint_state.set(BlueprintState::Unloaded);
+  }
+}
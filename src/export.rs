@@ -0,0 +1,211 @@
+//! Exporting the blueprint's blocks as a single Wavefront OBJ mesh.
+
+use std::{
+  fs::File,
+  io::{self, BufWriter, Write},
+  path::{Path, PathBuf},
+};
+
+use bevy::{mesh::PrimitiveTopology, prelude::*};
+
+use crate::{
+  BlockData, BlockIndex, blueprint::LoadedBlueprint, no_egui_keyboard_focus,
+  save::asset_file_path,
+};
+
+#[derive(Default)]
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems(Update, export_obj.run_if(no_egui_keyboard_focus));
+  }
+}
+
+/// Returns the path the blueprint at `path` is exported to as OBJ.
+pub fn obj_path(path: impl AsRef<Path>) -> PathBuf {
+  path.as_ref().with_extension("obj")
+}
+
+/// Writes every visible block as one OBJ mesh next to the blueprint file.
+///
+/// Only the geometry is written, without materials or selection.
+pub fn export_obj(
+  keycode: Res<ButtonInput<KeyCode>>,
+  meshes: Res<Assets<Mesh>>,
+  blueprint: Res<LoadedBlueprint>,
+  blocks: Query<
+    (&BlockIndex, &Mesh3d, &GlobalTransform, &InheritedVisibility),
+    With<BlockData>,
+  >,
+) {
+  // TODO: Make controls configurable.
+  if !(keycode.pressed(KeyCode::ControlLeft)
+    && keycode.just_pressed(KeyCode::KeyE))
+  {
+    return;
+  }
+
+  let Some(path) = blueprint.path() else {
+    warn!("Could not export the blueprint, as none is loaded from a file");
+    return;
+  };
+
+  let mut blocks = blocks
+    .iter()
+    .filter(|(.., visibility)| visibility.get())
+    .filter_map(|(index, mesh, transform, _)| {
+      Some((*index, meshes.get(&mesh.0)?, transform))
+    })
+    .collect::<Vec<_>>();
+  // Write the blocks in the order they are in the blueprint.
+  blocks.sort_by_key(|(index, ..)| *index);
+
+  let path = obj_path(asset_file_path(path.path()));
+  let result = File::create(&path).and_then(|file| {
+    let mut writer = BufWriter::new(file);
+    write_obj(
+      &mut writer,
+      blocks
+        .iter()
+        .map(|(index, mesh, transform)| (index.0, *mesh, *transform)),
+    )?;
+    writer.flush()
+  });
+  match result {
+    Ok(()) => {
+      info!("Exported {} blocks to {}", blocks.len(), path.display());
+    }
+    Err(error) => error!("Could not export the blueprint: {error}"),
+  }
+}
+
+/// Writes `meshes` to `writer` as one OBJ object each, with their vertices and
+/// normals baked into world space by their transforms.
+///
+/// Each mesh is named after its block index. Meshes that aren't triangle
+/// lists, have no positions, or whose data is only in the render world are
+/// skipped.
+pub fn write_obj<'a>(
+  mut writer: impl Write,
+  meshes: impl IntoIterator<Item = (usize, &'a Mesh, &'a GlobalTransform)>,
+) -> io::Result<()> {
+  // OBJ indices start at one and count every vertex written so far.
+  let mut first_vertex = 1;
+
+  for (index, mesh, transform) in meshes {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+      continue;
+    }
+    let Some(positions) = mesh
+      .try_attribute(Mesh::ATTRIBUTE_POSITION)
+      .ok()
+      .and_then(|values| values.as_float3())
+    else {
+      continue;
+    };
+    let normals = mesh
+      .try_attribute(Mesh::ATTRIBUTE_NORMAL)
+      .ok()
+      .and_then(|values| values.as_float3())
+      .filter(|normals| normals.len() == positions.len());
+
+    writeln!(writer, "o block_{index}")?;
+
+    let affine = transform.affine();
+    for &position in positions {
+      let Vec3 { x, y, z } = affine.transform_point3(position.into());
+      writeln!(writer, "v {x} {y} {z}")?;
+    }
+
+    if let Some(normals) = normals {
+      // Blocks are scaled unevenly, so normals need the inverse transpose to
+      // stay perpendicular to their faces.
+      let normal_matrix = Mat3::from(affine.matrix3).inverse().transpose();
+      for &normal in normals {
+        let Vec3 { x, y, z } =
+          (normal_matrix * Vec3::from(normal)).normalize_or_zero();
+        writeln!(writer, "vn {x} {y} {z}")?;
+      }
+    }
+
+    let indices = match mesh.try_indices_option().ok().flatten() {
+      Some(indices) => indices.iter().collect::<Vec<_>>(),
+      None => (0..positions.len()).collect(),
+    };
+    for triangle in indices.chunks_exact(3) {
+      let [a, b, c] = [triangle[0], triangle[1], triangle[2]]
+        .map(|vertex| vertex + first_vertex);
+      if normals.is_some() {
+        writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+      } else {
+        writeln!(writer, "f {a} {b} {c}")?;
+      }
+    }
+
+    first_vertex += positions.len();
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use core::f32::consts::FRAC_PI_4;
+
+  use bevy::math::Affine3A;
+
+  use super::*;
+
+  #[test]
+  fn write_obj_offsets_indices_and_transforms_normals() {
+    let mesh = Mesh::from(Cuboid::default());
+    // Scaling after a rotation skews the faces, so normals transformed like
+    // positions would no longer be perpendicular to them.
+    let skewed = GlobalTransform::from(
+      Affine3A::from_scale(Vec3::new(2.0, 1.0, 1.0))
+        * Affine3A::from_rotation_z(FRAC_PI_4),
+    );
+    let moved = GlobalTransform::from_xyz(5.0, 0.0, 0.0);
+
+    let mut obj = Vec::new();
+    write_obj(&mut obj, [(3, &mesh, &skewed), (7, &mesh, &moved)]).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    let lines = obj.lines().collect::<Vec<_>>();
+
+    // The second object's faces count on from the first object's vertices.
+    let vertices = mesh.count_vertices();
+    let second = lines.iter().position(|line| *line == "o block_7").unwrap();
+    let indices = mesh.indices().unwrap().iter().collect::<Vec<_>>();
+    let [a, b, c] =
+      [indices[0], indices[1], indices[2]].map(|index| index + vertices + 1);
+    assert_eq!(
+      lines[second + 2 * vertices + 1],
+      format!("f {a}//{a} {b}//{b} {c}//{c}")
+    );
+    assert_eq!(
+      lines.iter().filter(|line| line.starts_with("f ")).count(),
+      2 * indices.len() / 3
+    );
+
+    // The normal of the +X face is rotated to (1, 1, 0), then scaled by the
+    // inverse of the scale to (0.5, 1, 0).
+    let normals = mesh
+      .attribute(Mesh::ATTRIBUTE_NORMAL)
+      .and_then(|values| values.as_float3())
+      .unwrap();
+    let vertex = normals.iter().position(|normal| *normal == [1.0, 0.0, 0.0]);
+    let normal = lines
+      .iter()
+      .filter_map(|line| line.strip_prefix("vn "))
+      .nth(vertex.unwrap())
+      .unwrap()
+      .split(' ')
+      .map(|value| value.parse::<f32>().unwrap())
+      .collect::<Vec<_>>();
+    assert!(
+      Vec3::from_slice(&normal)
+        .abs_diff_eq(Vec3::new(0.5, 1.0, 0.0).normalize(), 1e-6)
+    );
+  }
+}
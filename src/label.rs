@@ -0,0 +1,210 @@
+//! Drawing the blueprint's labels as panels with text on them.
+
+use bevy::{
+  camera::RenderTarget, image::BevyDefault, prelude::*,
+  render::render_resource::TextureFormat,
+};
+
+use crate::blueprint::{self, Blueprint, BlueprintState, LoadedBlueprint};
+
+/// The height of a line of text on a label's texture, in pixels.
+const LINE_HEIGHT: u32 = 64;
+/// The width of a character on a label's texture, in pixels.
+///
+/// The font isn't monospaced, so this only has to be wide enough for most.
+const CHAR_WIDTH: u32 = 36;
+/// How many frames a label's camera renders its text before it is turned off.
+///
+/// The texture keeps the text once the camera stops, so one frame would do,
+/// were it not that the text may not be drawn until its pipelines compile.
+const LABEL_RENDER_FRAMES: u32 = 60;
+
+#[derive(Default)]
+pub struct LabelPlugin;
+
+impl Plugin for LabelPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_systems(OnEnter(BlueprintState::Loaded), spawn_labels)
+      .add_systems(Update, stop_label_cameras);
+  }
+}
+
+/// Marks the panel drawing a label, with the index of the label in
+/// [`BlueprintData::labels`].
+///
+/// [`BlueprintData::labels`]: crate::blueprint::BlueprintData::labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct LabelRef(pub usize);
+
+/// Marks the camera rendering a label's text into its texture, with how many
+/// more frames it renders for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct LabelCamera {
+  pub frames_left: u32,
+}
+
+/// Decodes a direction whose components each take `0` to `255` to span
+/// `-1.0` to `1.0`.
+fn unpack_direction(x: u8, y: u8, z: u8) -> Vec3 {
+  Vec3::new(x as f32, y as f32, z as f32) / 127.5 - 1.0
+}
+
+impl blueprint::Label {
+  /// The direction the front of the label faces.
+  pub fn direction(&self) -> Vec3 {
+    unpack_direction(self.dir_x, self.dir_y, self.dir_z)
+  }
+
+  /// The direction of the top of the label's text.
+  pub fn up(&self) -> Vec3 {
+    unpack_direction(self.up_x, self.up_y, self.up_z)
+  }
+
+  /// The size of the label's texture in pixels, fitting every line of its
+  /// text.
+  pub fn texture_size(&self) -> UVec2 {
+    let lines = self.text.lines().count().max(1) as u32;
+    let columns = self
+      .text
+      .lines()
+      .map(|line| line.chars().count())
+      .max()
+      .unwrap_or(0)
+      .max(1) as u32;
+
+    UVec2::new(columns * CHAR_WIDTH + LINE_HEIGHT / 2, lines * LINE_HEIGHT)
+  }
+}
+
+/// Spawns a panel for every label in the loaded blueprint, at its position and
+/// facing its direction.
+///
+/// The text is laid out as UI on a camera that renders into the panel's
+/// texture, and is only rendered for the first few frames, as it doesn't
+/// change. A line of text is `size` tall in the world, and the panel is as
+/// wide as its longest line. Panels can't be picked, so that they don't get in
+/// the way of selecting blocks.
+pub fn spawn_labels(
+  mut commands: Commands,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  for (index, label) in blueprint.data.labels.iter().enumerate() {
+    let size = label.texture_size();
+    let image = images.add(Image::new_target_texture(
+      size.x,
+      size.y,
+      TextureFormat::bevy_default(),
+      None,
+    ));
+
+    let panel_color = Color::srgba_u8(
+      label.panel_color.r,
+      label.panel_color.g,
+      label.panel_color.b,
+      label.panel_color.a,
+    );
+    let text_color = Color::srgb_u8(
+      label.text_color.r,
+      label.text_color.g,
+      label.text_color.b,
+    );
+
+    let camera = commands
+      .spawn((
+        DespawnOnExit(BlueprintState::Loaded),
+        LabelCamera {
+          frames_left: LABEL_RENDER_FRAMES,
+        },
+        Camera2d,
+        Camera {
+          // Render the text before the panel is drawn.
+          order: -1,
+          clear_color: ClearColorConfig::Custom(panel_color),
+          ..Default::default()
+        },
+        RenderTarget::from(image.clone()),
+      ))
+      .id();
+
+    let justify = if label.align_center != 0 {
+      Justify::Center
+    } else {
+      Justify::Left
+    };
+    commands
+      .spawn((
+        DespawnOnExit(BlueprintState::Loaded),
+        Node {
+          width: Val::Percent(100.0),
+          height: Val::Percent(100.0),
+          padding: UiRect::horizontal(Val::Px(LINE_HEIGHT as f32 / 4.0)),
+          flex_direction: FlexDirection::Column,
+          justify_content: JustifyContent::Center,
+          align_items: if label.align_center != 0 {
+            AlignItems::Center
+          } else {
+            AlignItems::Start
+          },
+          ..Default::default()
+        },
+        UiTargetCamera(camera),
+      ))
+      .with_child((
+        Text::new(label.text.clone()),
+        TextFont::from_font_size(LINE_HEIGHT as f32 * 0.75),
+        TextColor(text_color),
+        TextLayout::new_with_justify(justify),
+      ));
+
+    let scale = label.size / LINE_HEIGHT as f32;
+    let material = materials.add(StandardMaterial {
+      base_color_texture: Some(image),
+      metallic: label.metallic.unwrap_or(0) as f32 / 255.0,
+      perceptual_roughness: label.roughness as f32 / 255.0,
+      alpha_mode: if label.panel_color.a == u8::MAX {
+        AlphaMode::Opaque
+      } else {
+        AlphaMode::Blend
+      },
+      ..Default::default()
+    });
+
+    commands.spawn((
+      DespawnOnExit(BlueprintState::Loaded),
+      LabelRef(index),
+      Mesh3d(
+        meshes
+          .add(Rectangle::new(size.x as f32 * scale, size.y as f32 * scale)),
+      ),
+      MeshMaterial3d(material),
+      // The rectangle faces +Z, so point -Z away from the label's front.
+      Transform::from_translation(label.position.to_vec3())
+        .looking_to(-label.direction(), label.up()),
+      Pickable::IGNORE,
+    ));
+  }
+}
+
+/// Turns off each [`LabelCamera`] once it has rendered its text for
+/// [`LABEL_RENDER_FRAMES`], so that labels aren't drawn again every frame.
+pub fn stop_label_cameras(mut cameras: Query<(&mut LabelCamera, &mut Camera)>) {
+  for (mut label_camera, mut camera) in cameras.iter_mut() {
+    if !camera.is_active {
+      continue;
+    }
+
+    label_camera.frames_left = label_camera.frames_left.saturating_sub(1);
+    if label_camera.frames_left == 0 {
+      camera.is_active = false;
+    }
+  }
+}
@@ -1,20 +1,288 @@
 pub mod action;
 pub mod blueprint;
+pub mod camera;
+pub mod component;
+pub mod door;
+pub mod editor;
+pub mod export;
+pub mod label;
+pub mod overlay;
+pub mod pipe;
+pub mod place;
+pub mod save;
+pub mod select;
+pub mod shape;
+pub mod symmetry;
+pub mod ui;
+pub mod view;
 
-use bevy::{color::palettes::css, prelude::*};
+pub use blueprint::BlueprintPlugin;
+pub use camera::CameraPlugin;
+pub use editor::EditorPlugin;
 
-use crate::action::{ActionMessage, CombinedAction, SelectionAction};
+use std::collections::BTreeMap;
 
+use bevy::{
+  asset::AssetLoadFailedEvent,
+  color::palettes::css,
+  ecs::entity::EntityHashMap,
+  pbr::wireframe::Wireframe,
+  platform::collections::{HashMap, HashSet},
+  prelude::*,
+};
+use bevy_egui::EguiContexts;
+
+use crate::{
+  action::{ActionMessage, BoxedAction, CombinedAction, SelectionAction},
+  blueprint::{Block, Blueprint, BlueprintState, LoadedBlueprint},
+  place::EditorMode,
+};
+
+/// The size of a frame, in blocks.
+pub const FRAME_SIZE: f32 = 12.0;
+/// The thickness of the beams along the edges of a frame, in blocks.
+pub const BEAM_WIDTH: f32 = 0.5;
+
+pub fn setup_blueprint(
+  mut commands: Commands,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  common_assets: Res<CommonAssets>,
+  picking_filter: Res<PickingFilter>,
+  mut block_materials: ResMut<BlockMaterials>,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    error!("Could not set up the blueprint, as it is not loaded");
+    return;
+  };
+  // The palette belongs to the blueprint, so its materials don't carry over.
+  block_materials.clear();
+  spawn_blueprint(&mut commands, &common_assets, &picking_filter, blueprint);
+}
+
+/// Spawns the frames and blocks of a blueprint.
+pub fn spawn_blueprint(
+  commands: &mut Commands,
+  common_assets: &CommonAssets,
+  picking_filter: &PickingFilter,
+  blueprint: &Blueprint,
+) {
+  for (index, frame) in blueprint.data.frames.iter().enumerate() {
+    commands
+      .spawn((
+        DespawnOnExit(BlueprintState::Loaded),
+        FrameRef(index),
+        Transform::from_xyz(
+          frame.frame_x as f32 * FRAME_SIZE + FRAME_SIZE * 0.5,
+          frame.frame_y as f32 * FRAME_SIZE + FRAME_SIZE * 0.5,
+          frame.frame_z as f32 * FRAME_SIZE + FRAME_SIZE * 0.5,
+        ),
+        Visibility::default(),
+      ))
+      .with_children(|parent| {
+        parent.spawn((
+          FrameCube,
+          PickCategory::Frame,
+          picking_filter.pickable(PickCategory::Frame),
+          Mesh3d(common_assets.block(0)),
+          Transform::from_scale(Vec3::splat(FRAME_SIZE)),
+          Wireframe,
+        ));
+
+        for (_, axis, corner) in frame.beams() {
+          let along = axis.unit().as_vec3();
+          // The corner is relative to the frame's minimum corner, and the
+          // entity sits at its center.
+          let offset = (corner.as_vec3() - 0.5) * FRAME_SIZE * (1.0 - along);
+          parent.spawn((
+            Mesh3d(common_assets.beam.clone()),
+            MeshMaterial3d(common_assets.beam_material.clone()),
+            // Reach past the ends to fill the corners where beams meet.
+            Transform::from_translation(offset)
+              .with_scale(Vec3::splat(BEAM_WIDTH) + along * FRAME_SIZE),
+            Pickable::IGNORE,
+          ));
+        }
+      });
+  }
+
+  for (index, block) in blueprint.data.blocks.iter().enumerate() {
+    spawn_block(
+      commands,
+      common_assets,
+      picking_filter,
+      BlockIndex(index),
+      block.clone(),
+    );
+  }
+}
+
+/// Spawns a block.
+pub fn spawn_block(
+  commands: &mut Commands,
+  common_assets: &CommonAssets,
+  picking_filter: &PickingFilter,
+  index: BlockIndex,
+  block: Block,
+) -> Entity {
+  commands
+    .spawn(block_bundle(common_assets, picking_filter, index, block))
+    .observe(select_entity)
+    .observe(swap_to_selected_material)
+    .observe(swap_to_deselected_material)
+    .observe(swap_to_locked_material)
+    .observe(swap_to_unlocked_material)
+    .id()
+}
+
+/// Spawns a block from an exclusive context, such as an [`Action`].
+///
+/// [`Action`]: action::Action
+pub fn spawn_block_in_world(
+  world: &mut World,
+  index: BlockIndex,
+  block: Block,
+) -> Entity {
+  let bundle = block_bundle(
+    world.resource::<CommonAssets>(),
+    world.resource::<PickingFilter>(),
+    index,
+    block,
+  );
+
+  world
+    .spawn(bundle)
+    .observe(select_entity)
+    .observe(swap_to_selected_material)
+    .observe(swap_to_deselected_material)
+    .observe(swap_to_locked_material)
+    .observe(swap_to_unlocked_material)
+    .id()
+}
+
+/// Updates the transform and mesh of blocks whose [`BlockData`] has changed,
+/// such as after an [`Action`] edits it in place.
+///
+/// [`Action`]: action::Action
+pub fn sync_blocks(
+  common_assets: Res<CommonAssets>,
+  mut fallback_types: ResMut<FallbackBlockTypes>,
+  mut blocks: Query<
+    (&BlockData, &mut Transform, &mut Mesh3d),
+    Changed<BlockData>,
+  >,
+) {
+  for (block, mut transform, mut mesh) in blocks.iter_mut() {
+    *transform = block.world_transform();
+    mesh.0 = common_assets.block(block.r#type);
+
+    if !common_assets.has_block(block.r#type)
+      && fallback_types.insert(block.r#type)
+    {
+      warn!(
+        "Block type {} has no mesh, so it is rendered as a cube",
+        block.r#type
+      );
+    }
+  }
+}
+
+/// Despawns a block, deselecting it first so that the selection observers run
+/// while the entity is still intact, rather than as it is torn down.
+pub fn despawn_block(mut entity: EntityWorldMut) {
+  entity.remove::<Selected>();
+  entity.despawn();
+}
+
+fn block_bundle(
+  common_assets: &CommonAssets,
+  picking_filter: &PickingFilter,
+  index: BlockIndex,
+  block: Block,
+) -> impl Bundle + use<> {
+  (
+    DespawnOnExit(BlueprintState::Loaded),
+    Mesh3d(common_assets.block(block.r#type)),
+    MeshMaterial3d(common_assets.unselected.clone()),
+    block.world_transform(),
+    PickCategory::Block,
+    picking_filter.pickable(PickCategory::Block),
+    index,
+    BlockData(block),
+  )
+}
+
+/// The inputs controlling selection.
+///
+/// Insert it before adding [`EditorPlugin`] to remap them.
+///
+/// [`EditorPlugin`]: editor::EditorPlugin
+#[derive(Debug, Clone, Resource)]
+pub struct SelectionControls {
+  /// Clicked on a block to select only it.
+  pub select: PointerButton,
+  /// Any of these held while clicking adds the block to the selection, or
+  /// removes it, instead.
+  pub additive_modifiers: Vec<KeyCode>,
+  /// Any of these held while clicking selects every block of the same type
+  /// as the clicked one.
+  pub same_type_modifiers: Vec<KeyCode>,
+}
+
+impl Default for SelectionControls {
+  fn default() -> Self {
+    Self {
+      select: PointerButton::Primary,
+      additive_modifiers: vec![KeyCode::ShiftLeft, KeyCode::ShiftRight],
+      same_type_modifiers: vec![KeyCode::AltLeft, KeyCode::AltRight],
+    }
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn select_entity(
   event: On<Pointer<Click>>,
   keycode: Res<ButtonInput<KeyCode>>,
+  controls: Res<SelectionControls>,
+  mode: Res<State<EditorMode>>,
   query: Query<Entity, With<Selected>>,
+  locked: Query<(), With<Locked>>,
+  blocks: Query<(Entity, &BlockData, Has<Selected>), Without<Locked>>,
   mut messages: MessageWriter<ActionMessage>,
 ) {
-  // TODO: Make controls configurable.
-  if event.button == PointerButton::Primary {
-    // TODO: Make controls configurable.
-    if keycode.pressed(KeyCode::ShiftLeft) {
+  // Clicks place blocks instead in place mode.
+  if *mode.get() != EditorMode::Select || locked.contains(event.entity) {
+    return;
+  }
+
+  let additive =
+    keycode.any_pressed(controls.additive_modifiers.iter().copied());
+  if event.button == controls.select
+    && keycode.any_pressed(controls.same_type_modifiers.iter().copied())
+  {
+    let Ok((_, clicked, _)) = blocks.get(event.entity) else {
+      return;
+    };
+
+    // Selection actions toggle, so only push them for blocks that change.
+    let type_id = clicked.r#type;
+    let actions = blocks
+      .iter()
+      .filter_map(|(entity, block, selected)| {
+        let matches = block.r#type == type_id;
+        let toggle = if additive {
+          matches && !selected
+        } else {
+          matches != selected
+        };
+        toggle.then(|| Box::new(SelectionAction(entity)) as BoxedAction)
+      })
+      .collect::<CombinedAction>();
+    if !actions.is_empty() {
+      messages.write(ActionMessage::Push(Box::new(actions)));
+    }
+  } else if event.button == controls.select {
+    if additive {
       messages
         .write(ActionMessage::Push(Box::new(SelectionAction(event.entity))));
     } else {
@@ -43,10 +311,340 @@ pub fn swap_to_selected_material(
 pub fn swap_to_deselected_material(
   event: On<Remove, Selected>,
   common_assets: Res<CommonAssets>,
+  mut query: Query<(
+    &mut MeshMaterial3d<StandardMaterial>,
+    Option<&BlockMaterial>,
+  )>,
+) {
+  if let Ok((mut material, block_material)) = query.get_mut(event.entity) {
+    material.0 = block_material
+      .map_or(&common_assets.unselected, |block| &block.0)
+      .clone();
+  }
+}
+
+pub fn swap_to_locked_material(
+  event: On<Add, Locked>,
+  common_assets: Res<CommonAssets>,
   mut query: Query<&mut MeshMaterial3d<StandardMaterial>>,
 ) {
   if let Ok(mut material) = query.get_mut(event.entity) {
-    material.0 = common_assets.unselected.clone();
+    material.0 = common_assets.locked.clone();
+  }
+}
+
+pub fn swap_to_unlocked_material(
+  event: On<Remove, Locked>,
+  common_assets: Res<CommonAssets>,
+  mut query: Query<(
+    &mut MeshMaterial3d<StandardMaterial>,
+    Option<&BlockMaterial>,
+  )>,
+) {
+  if let Ok((mut material, block_material)) = query.get_mut(event.entity) {
+    material.0 = block_material
+      .map_or(&common_assets.unselected, |block| &block.0)
+      .clone();
+  }
+}
+
+/// The materials of the colors in the loaded blueprint's palette, by index,
+/// shared between every block of the same color.
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+pub struct BlockMaterials(pub HashMap<u8, Handle<StandardMaterial>>);
+
+/// The material a block is drawn with while it is neither selected nor
+/// locked, in its palette color.
+#[derive(Debug, Clone, Component)]
+pub struct BlockMaterial(pub Handle<StandardMaterial>);
+
+/// Gives blocks the material of their primary palette color when their
+/// [`BlockData`] changes, including when they are spawned. Blocks without a
+/// color, such as those referencing an empty palette slot, stay white.
+#[allow(clippy::type_complexity)]
+pub fn sync_block_materials(
+  mut commands: Commands,
+  common_assets: Res<CommonAssets>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+  mut block_materials: ResMut<BlockMaterials>,
+  mut blocks: Query<
+    (
+      Entity,
+      &BlockData,
+      &mut MeshMaterial3d<StandardMaterial>,
+      Has<Selected>,
+      Has<Locked>,
+    ),
+    Changed<BlockData>,
+  >,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  for (entity, block, mut material, selected, locked) in blocks.iter_mut() {
+    let index = block.colors.0;
+    let handle = match blueprint.data.color(index) {
+      Some(color) => block_materials
+        .entry(index)
+        .or_insert_with(|| materials.add(StandardMaterial::from(color)))
+        .clone(),
+      None => common_assets.unselected.clone(),
+    };
+
+    if !(selected || locked) {
+      material.0 = handle.clone();
+    }
+    commands.entity(entity).insert(BlockMaterial(handle));
+  }
+}
+
+/// Logs the block types used by the loaded blueprint, and which of them are
+/// rendered as fallback cubes because there is no mesh for them.
+pub fn report_block_types(
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  common_assets: Res<CommonAssets>,
+  mut fallback_types: ResMut<FallbackBlockTypes>,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  let mut counts = BTreeMap::<u8, usize>::new();
+  for block in blueprint.data.blocks.iter() {
+    *counts.entry(block.r#type).or_default() += 1;
+  }
+
+  info!(
+    "Blueprint uses block types {:?}",
+    counts.keys().collect::<Vec<_>>()
+  );
+
+  let fallbacks = counts
+    .iter()
+    .filter(|(id, _)| !common_assets.has_block(**id))
+    .collect::<Vec<_>>();
+  if !fallbacks.is_empty() {
+    warn!(
+      "Blueprint uses {} block types without a mesh. These are rendered as cubes, so the view is approximate",
+      fallbacks.len()
+    );
+    for (id, count) in fallbacks {
+      warn!("Block type {id} is used by {count} blocks");
+      fallback_types.insert(*id);
+    }
+  }
+}
+
+/// The block types without a mesh that have been warned about, so that each
+/// is only warned about once as blocks of it are spawned or changed.
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+pub struct FallbackBlockTypes(pub HashSet<u8>);
+
+/// Logs which components of the loaded blueprint host sub-builds, and warns
+/// about composite builds referencing missing components.
+pub fn report_composite_builds(
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  for link in blueprint.data.composite_links() {
+    info!(
+      "Component {} ({}) hosts sub-build {}",
+      link.component_index, link.component.r#type, link.slave_build_id
+    );
+  }
+
+  for build in blueprint.data.composite_builds.iter() {
+    if blueprint.data.component(build.component as usize).is_none() {
+      warn!(
+        "Sub-build {} is hosted by component {}, which does not exist",
+        build.slave_build_id, build.component
+      );
+    }
+  }
+}
+
+/// The blueprint data of a spawned block.
+#[derive(Debug, Clone, Component, Deref, DerefMut)]
+pub struct BlockData(pub Block);
+
+/// The index of a spawned block in [`BlueprintData::blocks`].
+///
+/// [`BlueprintData::blocks`]: blueprint::BlueprintData::blocks
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component,
+)]
+pub struct BlockIndex(pub usize);
+
+/// Maps between spawned blocks and their [`BlockIndex`], kept up to date as
+/// blocks are spawned and despawned.
+#[derive(Debug, Default, Resource)]
+pub struct BlockEntities {
+  indices: EntityHashMap<usize>,
+  entities: HashMap<usize, Entity>,
+}
+
+impl BlockEntities {
+  /// The index of the block spawned as `entity`, if it is one.
+  pub fn index(&self, entity: Entity) -> Option<usize> {
+    self.indices.get(&entity).copied()
+  }
+
+  /// The entity the block at `index` is spawned as, if it is spawned.
+  pub fn entity(&self, index: usize) -> Option<Entity> {
+    self.entities.get(&index).copied()
+  }
+}
+
+pub fn track_block_entity(
+  event: On<Add, BlockIndex>,
+  indices: Query<&BlockIndex>,
+  mut block_entities: ResMut<BlockEntities>,
+) {
+  if let Ok(index) = indices.get(event.entity) {
+    block_entities.indices.insert(event.entity, index.0);
+    block_entities.entities.insert(index.0, event.entity);
+  }
+}
+
+pub fn untrack_block_entity(
+  event: On<Remove, BlockIndex>,
+  mut block_entities: ResMut<BlockEntities>,
+) {
+  let Some(index) = block_entities.indices.remove(&event.entity) else {
+    return;
+  };
+
+  // Another block may have taken the index since, such as a deleted block
+  // being restored before the old entity is gone.
+  if block_entities.entities.get(&index) == Some(&event.entity) {
+    block_entities.entities.remove(&index);
+  }
+}
+
+/// The index of a spawned frame in [`BlueprintData::frames`].
+///
+/// [`BlueprintData::frames`]: blueprint::BlueprintData::frames
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Component,
+)]
+pub struct FrameRef(pub usize);
+
+/// Marks the cube filling a frame, which is only shown while
+/// [`FrameCubes`] says so.
+///
+/// [`FrameCubes`]: view::FrameCubes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct FrameCube;
+
+/// Whether egui wants keyboard input, such as while a text field has focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct EguiKeyboardFocus(pub bool);
+
+pub fn update_egui_keyboard_focus(
+  mut contexts: EguiContexts,
+  mut focus: ResMut<EguiKeyboardFocus>,
+) {
+  focus.0 = contexts
+    .ctx_mut()
+    .is_ok_and(|ctx| ctx.wants_keyboard_input());
+}
+
+/// A run condition for keyboard shortcuts, which holds unless egui wants the
+/// keyboard, so that typing into a text field doesn't trigger them.
+///
+/// Run conditions can't borrow the egui context mutably, so this reads the
+/// [`EguiKeyboardFocus`] kept up to date by [`update_egui_keyboard_focus`].
+pub fn no_egui_keyboard_focus(focus: Res<EguiKeyboardFocus>) -> bool {
+  !focus.0
+}
+
+/// Returns the minimum and maximum corners of the box enclosing `blocks`, in
+/// blocks, or [`None`] if there are no blocks.
+pub fn selection_bounds<'a>(
+  blocks: impl IntoIterator<Item = &'a Block>,
+) -> Option<(IVec3, IVec3)> {
+  blocks.into_iter().fold(None, |bounds, block| {
+    let (min, max) = (block.min(), block.min() + block.size());
+    Some(match bounds {
+      Some((bounds_min, bounds_max)) => {
+        (min.min(bounds_min), max.max(bounds_max))
+      }
+      None => (min, max),
+    })
+  })
+}
+
+/// The kind of blueprint element an entity represents, which decides whether
+/// it can be picked according to the [`PickingFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub enum PickCategory {
+  Block,
+  Frame,
+  Component,
+}
+
+/// Controls which [`PickCategory`]s absorb clicks, so that blocks behind
+/// frames and components can be clicked through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct PickingFilter {
+  /// Only blocks can be picked, regardless of the other categories.
+  pub blocks_only: bool,
+  pub blocks: bool,
+  pub frames: bool,
+  pub components: bool,
+}
+
+impl Default for PickingFilter {
+  fn default() -> Self {
+    Self {
+      blocks_only: true,
+      blocks: true,
+      frames: false,
+      components: true,
+    }
+  }
+}
+
+impl PickingFilter {
+  /// Whether entities of `category` can be picked.
+  pub fn allows(&self, category: PickCategory) -> bool {
+    match category {
+      PickCategory::Block => self.blocks_only || self.blocks,
+      PickCategory::Frame => !self.blocks_only && self.frames,
+      PickCategory::Component => !self.blocks_only && self.components,
+    }
+  }
+
+  /// Returns the [`Pickable`] for entities of `category`.
+  pub fn pickable(&self, category: PickCategory) -> Pickable {
+    if self.allows(category) {
+      Pickable::default()
+    } else {
+      Pickable::IGNORE
+    }
+  }
+}
+
+/// Updates the [`Pickable`] of spawned entities when the [`PickingFilter`]
+/// changes.
+pub fn apply_picking_filter(
+  picking_filter: Res<PickingFilter>,
+  mut query: Query<(&PickCategory, &mut Pickable)>,
+) {
+  if !picking_filter.is_changed() {
+    return;
+  }
+
+  for (category, mut pickable) in query.iter_mut() {
+    *pickable = picking_filter.pickable(*category);
   }
 }
 
@@ -54,14 +652,42 @@ pub fn swap_to_deselected_material(
 #[derive(Component)]
 pub struct Selected;
 
+/// Marks a block as locked, so that it can't be selected or edited until it is
+/// unlocked.
+#[derive(Component)]
+pub struct Locked;
+
+/// The meshes and materials shared by every blueprint.
+///
+/// This is the only place block types are mapped to meshes. Types 0 to 52
+/// each have their own mesh, loaded from `blocks/<type>.obj`, which covers the
+/// corners listed for the type in [`CORNER_MASKS`], so rotated variants are
+/// separate types with separate meshes. Every other type below
+/// [`BLOCK_TYPE_COUNT`] has no known shape and is drawn with the type 0 cube,
+/// as is any type whose mesh fails to load.
+///
+/// [`CORNER_MASKS`]: shape::CORNER_MASKS
+/// [`BLOCK_TYPE_COUNT`]: blueprint::BLOCK_TYPE_COUNT
 #[derive(Resource)]
 pub struct CommonAssets {
+  /// The mesh of each block type with a known shape, by type.
+  // TODO: Add meshes for types 53 to 79 once their shapes are known. Neither
+  // the assets nor `CORNER_MASKS` describe them, so they are drawn as cubes.
   blocks: [Handle<Mesh>; 53],
+  /// A unit cube standing in for block meshes that failed to load.
+  pub placeholder: Handle<Mesh>,
   pub unselected: Handle<StandardMaterial>,
   pub selected: Handle<StandardMaterial>,
+  /// Dimmed, for [`Locked`] blocks.
+  pub locked: Handle<StandardMaterial>,
+  /// A unit cube, scaled to each beam of a frame.
+  pub beam: Handle<Mesh>,
+  pub beam_material: Handle<StandardMaterial>,
 }
 
 impl CommonAssets {
+  /// The mesh block type `id` is drawn with, falling back to the cube for
+  /// types without a known shape.
   pub fn block(&self, id: u8) -> Handle<Mesh> {
     self
       .blocks
@@ -69,6 +695,46 @@ impl CommonAssets {
       .cloned()
       .unwrap_or_else(|| self.blocks[0].clone())
   }
+
+  /// Whether there is a mesh for the block type, rather than a fallback cube.
+  pub fn has_block(&self, id: u8) -> bool {
+    self
+      .blocks
+      .get(id as usize)
+      .is_some_and(|block| *block != self.placeholder)
+  }
+}
+
+/// Swaps block meshes that failed to load, such as when their file is
+/// missing, for the placeholder, both in [`CommonAssets`] and on the entities
+/// already using them.
+pub fn replace_missing_block_meshes(
+  mut failed: MessageReader<AssetLoadFailedEvent<Mesh>>,
+  mut common_assets: ResMut<CommonAssets>,
+  mut meshes: Query<&mut Mesh3d>,
+) {
+  for event in failed.read() {
+    let placeholder = common_assets.placeholder.clone();
+    let Some(block) = common_assets
+      .blocks
+      .iter_mut()
+      .find(|block| block.id() == event.id)
+    else {
+      continue;
+    };
+
+    warn!(
+      "Could not load block mesh {}, so a cube is shown instead: {}",
+      event.path, event.error
+    );
+    *block = placeholder.clone();
+
+    for mut mesh in meshes.iter_mut() {
+      if mesh.id() == event.id {
+        mesh.0 = placeholder.clone();
+      }
+    }
+  }
 }
 
 impl FromWorld for CommonAssets {
@@ -76,6 +742,17 @@ impl FromWorld for CommonAssets {
     let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
     let unselected = materials.add(Color::WHITE);
     let selected = materials.add(Color::from(css::BLUE));
+    let locked = materials.add(Color::from(css::GRAY));
+    let beam_material = materials.add(StandardMaterial {
+      base_color: Color::from(css::DARK_GRAY),
+      metallic: 0.8,
+      perceptual_roughness: 0.5,
+      ..Default::default()
+    });
+
+    let mut meshes = world.resource_mut::<Assets<Mesh>>();
+    let placeholder = meshes.add(Cuboid::from_length(1.0));
+    let beam = meshes.add(Cuboid::from_length(1.0));
 
     let asset_server = world.resource::<AssetServer>();
 
@@ -135,8 +812,177 @@ impl FromWorld for CommonAssets {
         asset_server.load("blocks/51.obj"),
         asset_server.load("blocks/52.obj"),
       ],
+      placeholder,
       unselected,
       selected,
+      locked,
+      beam,
+      beam_material,
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::blueprint::{Blueprint, FillPattern};
+
+  #[derive(Resource, Default)]
+  struct Deselected(Vec<Option<BlockIndex>>);
+
+  #[test]
+  fn despawn_block_deselects_intact_block() {
+    let mut world = World::new();
+    world.init_resource::<Deselected>();
+    world.add_observer(
+      |event: On<Remove, Selected>,
+       query: Query<&BlockIndex>,
+       mut deselected: ResMut<Deselected>| {
+        deselected.0.push(query.get(event.entity).ok().copied());
+      },
+    );
+    let selected = world.spawn((BlockIndex(0), Selected)).id();
+    let unselected = world.spawn(BlockIndex(1)).id();
+
+    despawn_block(world.entity_mut(selected));
+    despawn_block(world.entity_mut(unselected));
+    assert_eq!(world.resource::<Deselected>().0, [Some(BlockIndex(0))]);
+    assert!(world.get_entity(selected).is_err());
+    assert!(world.get_entity(unselected).is_err());
+  }
+
+  #[test]
+  fn selection_outlines_match_block_sizes() {
+    let blueprint =
+      Blueprint::synthetic(UVec3::new(1, 1, 2), FillPattern::Solid);
+    let mut blocks = blueprint.data.blocks;
+    // A 3x1x1 block at the origin, and a unit block above it in the next
+    // frame along Z.
+    blocks[0].size_x = 2;
+    blocks[1].set_min(IVec3::new(1, 1, 12));
+
+    assert_eq!(blocks[0].world_transform().scale, Vec3::new(3.0, 1.0, 1.0));
+    assert_eq!(
+      selection_bounds(&blocks),
+      Some((IVec3::ZERO, IVec3::new(3, 2, 13))),
+    );
+    assert_eq!(
+      selection_bounds(&blocks[1..]),
+      Some((IVec3::new(1, 1, 12), IVec3::new(2, 2, 13))),
+    );
+    assert_eq!(selection_bounds(&[]), None);
+  }
+
+  #[test]
+  fn picking_filter_allows_categories() {
+    let filter = PickingFilter::default();
+    assert!(filter.allows(PickCategory::Block));
+    assert!(!filter.allows(PickCategory::Frame));
+    assert!(!filter.allows(PickCategory::Component));
+
+    let filter = PickingFilter {
+      blocks_only: false,
+      blocks: false,
+      frames: true,
+      components: false,
+    };
+    assert!(!filter.allows(PickCategory::Block));
+    assert!(filter.allows(PickCategory::Frame));
+    assert!(!filter.allows(PickCategory::Component));
+  }
+
+  #[test]
+  fn apply_picking_filter_updates_spawned_entities() {
+    use bevy::ecs::system::RunSystemOnce;
+
+    let mut world = World::new();
+    let filter = PickingFilter::default();
+    let block = world
+      .spawn((PickCategory::Block, filter.pickable(PickCategory::Block)))
+      .id();
+    let frame = world
+      .spawn((PickCategory::Frame, filter.pickable(PickCategory::Frame)))
+      .id();
+    assert!(!world.get::<Pickable>(frame).unwrap().is_hoverable);
+
+    world.insert_resource(PickingFilter {
+      blocks_only: false,
+      blocks: false,
+      frames: true,
+      components: true,
+    });
+    world.run_system_once(apply_picking_filter).unwrap();
+    assert!(!world.get::<Pickable>(block).unwrap().is_hoverable);
+    assert!(world.get::<Pickable>(frame).unwrap().is_hoverable);
+  }
+
+  #[test]
+  fn picking_filter_clicks_through_to_blocks() {
+    use bevy::{
+      camera::primitives::MeshAabb, ecs::system::RunSystemOnce,
+      picking::mesh_picking::ray_cast::RayCastVisibility,
+    };
+
+    // The ray cast culls in parallel on the compute pool.
+    bevy::tasks::ComputeTaskPool::get_or_init(Default::default);
+    let mut world = World::new();
+    world.init_resource::<Assets<Mesh>>();
+    let mesh = Mesh::from(Cuboid::default());
+    let aabb = mesh.compute_aabb().unwrap();
+    let mesh = world.resource_mut::<Assets<Mesh>>().add(mesh);
+    let mut spawn = |category, z| {
+      world
+        .spawn((
+          category,
+          PickingFilter::default().pickable(category),
+          Mesh3d(mesh.clone()),
+          aabb,
+          GlobalTransform::from_xyz(0.0, 0.0, z),
+          InheritedVisibility::VISIBLE,
+          ViewVisibility::default(),
+        ))
+        .id()
+    };
+
+    // A component in front of a block along the ray.
+    let block = spawn(PickCategory::Block, 0.0);
+    let component = spawn(PickCategory::Component, 2.0);
+    let nearest = |world: &mut World| {
+      world
+        .run_system_once(
+          |mut ray_cast: MeshRayCast, pickables: Query<&Pickable>| {
+            // The same filters as the mesh picking backend.
+            let filter = |entity| {
+              pickables.get(entity).ok().is_none_or(|p| p.is_hoverable)
+            };
+            let early_exit_test = |entity| {
+              pickables.get(entity).is_ok_and(|p| p.should_block_lower)
+            };
+            let settings = MeshRayCastSettings::default()
+              .with_visibility(RayCastVisibility::Any)
+              .with_filter(&filter)
+              .with_early_exit_test(&early_exit_test);
+            let ray = Ray3d::new(Vec3::new(0.1, 0.2, 5.0), Dir3::NEG_Z);
+            ray_cast
+              .cast_ray(ray, &settings)
+              .first()
+              .map(|(entity, _)| *entity)
+          },
+        )
+        .unwrap()
+    };
+
+    // The default filter clicks through the component to the block.
+    assert_eq!(nearest(&mut world), Some(block));
+
+    // Once components are pickable, the component absorbs the click.
+    let filter = PickingFilter {
+      blocks_only: false,
+      ..Default::default()
+    };
+    world
+      .entity_mut(component)
+      .insert(filter.pickable(PickCategory::Component));
+    assert_eq!(nearest(&mut world), Some(component));
+  }
+}
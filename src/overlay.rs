@@ -0,0 +1,386 @@
+//! Overlays drawn with egui on top of the 3D view.
+
+use bevy::{
+  color::palettes::css,
+  diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+  platform::collections::HashSet,
+  prelude::*,
+};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+use crate::{
+  BlockData, BlockIndex, Selected,
+  blueprint::{Blueprint, LoadedBlueprint},
+  no_egui_keyboard_focus, selection_bounds,
+};
+
+#[derive(Default)]
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<ComponentLabels>()
+      .init_resource::<CompositeBuildMarkers>()
+      .init_resource::<PickNormals>()
+      .init_resource::<OverflowHighlights>()
+      .init_resource::<RenderStats>()
+      .add_observer(record_pick_normal)
+      .add_systems(
+        Update,
+        (
+          draw_composite_build_markers,
+          draw_selection_outlines,
+          draw_pick_normal,
+          draw_overflow_highlights,
+          (
+            toggle_render_stats.run_if(no_egui_keyboard_focus),
+            collect_render_stats,
+          )
+            .chain(),
+        ),
+      )
+      .add_systems(
+        EguiPrimaryContextPass,
+        (
+          show_component_labels,
+          show_selection_summary,
+          show_render_stats,
+        ),
+      );
+
+    if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+      app.add_plugins(FrameTimeDiagnosticsPlugin::default());
+    }
+  }
+}
+
+/// Settings for the floating labels naming each component.
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
+pub struct ComponentLabels {
+  pub enabled: bool,
+  /// Labels further than this from the camera aren't drawn.
+  pub max_distance: f32,
+}
+
+impl Default for ComponentLabels {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      max_distance: 200.0,
+    }
+  }
+}
+
+/// Settings for the markers on components hosting sub-builds.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Resource)]
+pub struct CompositeBuildMarkers {
+  pub enabled: bool,
+}
+
+/// Settings for debugging picking, by drawing the normal of the last clicked
+/// point as an arrow.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Resource)]
+pub struct PickNormals {
+  pub enabled: bool,
+  /// The position and normal of the last click that hit something, if the
+  /// picking backend reported them.
+  pub last: Option<(Vec3, Vec3)>,
+}
+
+/// Settings for outlining blocks that reach past their frame, which is
+/// usually a sign of bad data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct OverflowHighlights {
+  pub enabled: bool,
+}
+
+impl Default for OverflowHighlights {
+  fn default() -> Self {
+    Self { enabled: true }
+  }
+}
+
+/// What is being rendered, to tell whether the block count, culling or draw
+/// calls are what slows the editor down. Only collected while shown.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Resource)]
+pub struct RenderStats {
+  pub enabled: bool,
+  /// Every spawned block.
+  pub blocks: usize,
+  /// The blocks that survived frustum culling last frame.
+  pub visible_blocks: usize,
+  /// The distinct mesh and material pairs among the visible blocks.
+  ///
+  /// The renderer draws every block of a pair in one instanced batch, so this
+  /// approximates the draw calls for blocks.
+  pub batches: usize,
+  /// The smoothed frame time, in milliseconds.
+  pub frame_time: Option<f64>,
+}
+
+pub fn toggle_render_stats(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut render_stats: ResMut<RenderStats>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::F3) {
+    render_stats.enabled = !render_stats.enabled;
+  }
+}
+
+/// Counts the blocks and their batches into the [`RenderStats`] while they are
+/// shown.
+pub fn collect_render_stats(
+  mut render_stats: ResMut<RenderStats>,
+  blocks: Query<
+    (&ViewVisibility, &Mesh3d, &MeshMaterial3d<StandardMaterial>),
+    With<BlockData>,
+  >,
+  diagnostics: Res<DiagnosticsStore>,
+) {
+  if !render_stats.enabled {
+    return;
+  }
+
+  let visible = blocks
+    .iter()
+    .filter(|(visibility, ..)| visibility.get())
+    .collect::<Vec<_>>();
+  render_stats.blocks = blocks.iter().count();
+  render_stats.visible_blocks = visible.len();
+  render_stats.batches = visible
+    .iter()
+    .map(|(_, mesh, material)| (mesh.id(), material.id()))
+    .collect::<HashSet<_>>()
+    .len();
+  render_stats.frame_time = diagnostics
+    .get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+    .and_then(|frame_time| frame_time.smoothed());
+}
+
+/// Shows the [`RenderStats`] in the top right corner while they are enabled.
+pub fn show_render_stats(
+  mut contexts: EguiContexts,
+  render_stats: Res<RenderStats>,
+) -> Result {
+  if !render_stats.enabled {
+    return Ok(());
+  }
+
+  let ctx = contexts.ctx_mut()?;
+  egui::Area::new(egui::Id::new("render_stats"))
+    .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+    .show(ctx, |ui| {
+      ui.label(format!("Blocks: {}", render_stats.blocks));
+      ui.label(format!("Visible blocks: {}", render_stats.visible_blocks));
+      ui.label(format!("Block batches: {}", render_stats.batches));
+      if let Some(frame_time) = render_stats.frame_time {
+        ui.label(format!("Frame time: {frame_time:.2} ms"));
+      }
+    });
+
+  Ok(())
+}
+
+/// Draws `text` centered above the screen position of `position`, if it is in
+/// view of the camera.
+pub fn draw_world_label(
+  painter: &egui::Painter,
+  camera: &Camera,
+  camera_transform: &GlobalTransform,
+  position: Vec3,
+  text: impl ToString,
+  color: egui::Color32,
+) {
+  let Ok(viewport) = camera.world_to_viewport(camera_transform, position)
+  else {
+    return;
+  };
+
+  painter.text(
+    egui::pos2(viewport.x, viewport.y),
+    egui::Align2::CENTER_BOTTOM,
+    text,
+    egui::FontId::proportional(14.0),
+    color,
+  );
+}
+
+/// Draws each component's alias, or its type if it has none, above it.
+pub fn show_component_labels(
+  mut contexts: EguiContexts,
+  component_labels: Res<ComponentLabels>,
+  camera: Single<(&Camera, &GlobalTransform), With<Camera3d>>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+) -> Result {
+  if !component_labels.enabled {
+    return Ok(());
+  }
+
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return Ok(());
+  };
+
+  let ctx = contexts.ctx_mut()?;
+  let painter = ctx.layer_painter(egui::LayerId::background());
+  let (camera, camera_transform) = *camera;
+
+  for component in blueprint.data.components.iter() {
+    let position = component.transform().translation;
+    if camera_transform.translation().distance(position)
+      > component_labels.max_distance
+    {
+      continue;
+    }
+
+    draw_world_label(
+      &painter,
+      camera,
+      camera_transform,
+      position + Vec3::Y,
+      component.alias.as_deref().unwrap_or(&component.r#type),
+      egui::Color32::WHITE,
+    );
+  }
+
+  Ok(())
+}
+
+/// Shows how many blocks are selected and the size of the box enclosing them,
+/// or the index and type of a single selected block.
+pub fn show_selection_summary(
+  mut contexts: EguiContexts,
+  selection: Query<(&BlockIndex, &BlockData), With<Selected>>,
+) -> Result {
+  let Some((min, max)) =
+    selection_bounds(selection.iter().map(|(_, block)| &block.0))
+  else {
+    return Ok(());
+  };
+  let size = max - min;
+
+  let ctx = contexts.ctx_mut()?;
+  egui::Area::new(egui::Id::new("selection_summary"))
+    .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8.0, -8.0))
+    .show(ctx, |ui| {
+      if let Ok((index, block)) = selection.single() {
+        ui.label(format!("Block {} of type {}", index.0, block.r#type));
+      } else {
+        ui.label(format!("{} blocks selected", selection.iter().count()));
+      }
+      ui.label(format!("Size: {}×{}×{}", size.x, size.y, size.z));
+    });
+
+  Ok(())
+}
+
+/// Marks each component hosting a sub-build with a sphere.
+///
+/// The sub-builds themselves aren't part of the blueprint, so only the hosting
+/// components are marked.
+pub fn draw_composite_build_markers(
+  mut gizmos: Gizmos,
+  composite_build_markers: Res<CompositeBuildMarkers>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+) {
+  if !composite_build_markers.enabled {
+    return;
+  }
+
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  for link in blueprint.data.composite_links() {
+    gizmos.sphere(
+      link.component.position.to_vec3(),
+      1.0,
+      Color::from(css::ORANGE),
+    );
+  }
+}
+
+/// Outlines each selected block, and the box enclosing the whole selection if
+/// there is more than one block.
+///
+/// A block's transform scales the unit cube to the block's size, so the same
+/// transform gives a unit cube gizmo the size of the block.
+pub fn draw_selection_outlines(
+  mut gizmos: Gizmos,
+  selection: Query<(&GlobalTransform, &BlockData), With<Selected>>,
+) {
+  for (transform, _) in selection.iter() {
+    gizmos.cube(*transform, Color::from(css::AQUA));
+  }
+
+  if selection.iter().count() < 2 {
+    return;
+  }
+
+  if let Some((min, max)) =
+    selection_bounds(selection.iter().map(|(_, block)| &block.0))
+  {
+    let (min, max) = (min.as_vec3(), max.as_vec3());
+    gizmos.cube(
+      Transform::from_translation((min + max) * 0.5).with_scale(max - min),
+      Color::from(css::YELLOW),
+    );
+  }
+}
+
+/// Records the position and normal of each click into [`PickNormals`] while
+/// it is enabled.
+pub fn record_pick_normal(
+  event: On<Pointer<Click>>,
+  mut pick_normals: ResMut<PickNormals>,
+) {
+  if !pick_normals.enabled {
+    return;
+  }
+
+  match (event.hit.position, event.hit.normal) {
+    (Some(position), Some(normal)) => {
+      pick_normals.last = Some((position, normal));
+    }
+    _ => {
+      warn!(
+        "Click on entity {} did not report a position and normal",
+        event.entity
+      );
+      pick_normals.last = None;
+    }
+  }
+}
+
+/// Draws the normal of the last click recorded in [`PickNormals`], one block
+/// long.
+pub fn draw_pick_normal(mut gizmos: Gizmos, pick_normals: Res<PickNormals>) {
+  if !pick_normals.enabled {
+    return;
+  }
+
+  if let Some((position, normal)) = pick_normals.last {
+    gizmos.arrow(position, position + normal, Color::from(css::LIME));
+  }
+}
+
+/// Outlines each block reaching past its frame in red while
+/// [`OverflowHighlights`] is enabled.
+pub fn draw_overflow_highlights(
+  mut gizmos: Gizmos,
+  overflow_highlights: Res<OverflowHighlights>,
+  blocks: Query<(&GlobalTransform, &BlockData)>,
+) {
+  if !overflow_highlights.enabled {
+    return;
+  }
+
+  for (transform, block) in blocks.iter() {
+    if block.frame_overflow() != IVec3::ZERO {
+      gizmos.cube(*transform, Color::from(css::RED));
+    }
+  }
+}
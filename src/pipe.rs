@@ -0,0 +1,122 @@
+//! Drawing the pipes between components.
+
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::blueprint::{
+  Blueprint, BlueprintState, LoadedBlueprint, PipeSegment,
+};
+
+#[derive(Default)]
+pub struct PipePlugin;
+
+impl Plugin for PipePlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems(OnEnter(BlueprintState::Loaded), spawn_pipes);
+  }
+}
+
+/// Marks an entity drawing a segment of a pipe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct PipeRef {
+  /// The index of the pipe in [`BlueprintData::pipes`].
+  ///
+  /// [`BlueprintData::pipes`]: crate::blueprint::BlueprintData::pipes
+  pub pipe: usize,
+  /// The index of the segment in [`Pipe::segments`].
+  ///
+  /// [`Pipe::segments`]: crate::blueprint::Pipe::segments
+  pub segment: usize,
+}
+
+impl PipeSegment {
+  /// The direction the segment runs in from its start, taking `dir` to be
+  /// +X, -X, +Y, -Y, +Z or -Z in that order, like the faces of a block.
+  pub fn direction(&self) -> Dir3 {
+    match self.dir {
+      0 => Dir3::X,
+      1 => Dir3::NEG_X,
+      2 => Dir3::Y,
+      3 => Dir3::NEG_Y,
+      4 => Dir3::Z,
+      _ => Dir3::NEG_Z,
+    }
+  }
+
+  /// The material the segment is drawn with, from its color and finish.
+  pub fn material(&self) -> StandardMaterial {
+    StandardMaterial {
+      base_color: Color::srgba_u8(self.r, self.g, self.b, self.a),
+      metallic: if self.metal || self.chrome { 1.0 } else { 0.0 },
+      perceptual_roughness: if self.chrome {
+        0.05
+      } else if self.glossy {
+        0.3
+      } else {
+        0.8
+      },
+      alpha_mode: if self.a == u8::MAX {
+        AlphaMode::Opaque
+      } else {
+        AlphaMode::Blend
+      },
+      ..Default::default()
+    }
+  }
+}
+
+/// Spawns a cylinder for every segment of every pipe in the loaded blueprint.
+///
+/// Flexible segments are drawn straight, as their curve isn't known. Pipes
+/// can't be picked, so that they don't get in the way of selecting blocks.
+pub fn spawn_pipes(
+  mut commands: Commands,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  if blueprint.data.pipes.is_empty() {
+    return;
+  }
+
+  // A unit cylinder along Y, scaled to each segment.
+  let mesh = meshes.add(Cylinder::new(1.0, 1.0));
+  // Segments of the same color and finish share a material.
+  let mut segment_materials = HashMap::new();
+
+  for (pipe_index, pipe) in blueprint.data.pipes.iter().enumerate() {
+    let radius = pipe.radius as f32;
+    for (segment_index, segment) in pipe.segments.iter().enumerate() {
+      let key = (
+        [segment.r, segment.g, segment.b, segment.a],
+        [segment.chrome, segment.glossy, segment.metal],
+      );
+      let material = segment_materials
+        .entry(key)
+        .or_insert_with(|| materials.add(segment.material()))
+        .clone();
+
+      let direction = segment.direction();
+      let length = segment.length as f32;
+      commands.spawn((
+        DespawnOnExit(BlueprintState::Loaded),
+        PipeRef {
+          pipe: pipe_index,
+          segment: segment_index,
+        },
+        Mesh3d(mesh.clone()),
+        MeshMaterial3d(material),
+        Transform::from_translation(
+          segment.start.to_vec3() + direction * length * 0.5,
+        )
+        .with_rotation(Quat::from_rotation_arc(Vec3::Y, *direction))
+        .with_scale(Vec3::new(radius, length, radius)),
+        Pickable::IGNORE,
+      ));
+    }
+  }
+}
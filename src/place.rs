@@ -0,0 +1,578 @@
+//! Placing new blocks into the blueprint.
+
+use bevy::{platform::collections::HashSet, prelude::*, window::PrimaryWindow};
+use bevy_egui::EguiContexts;
+
+use crate::{
+  BlockData, CommonAssets, FRAME_SIZE,
+  action::{ActionMessage, BoxedAction, CombinedAction, PlaceAction},
+  blueprint::{
+    BLOCK_TYPE_COUNT, Block, Blueprint, FillPattern, Frame, LoadedBlueprint,
+    world_to_frame_pos,
+  },
+  no_egui_keyboard_focus,
+  shape::{GridAxis, rotate_block_type},
+  symmetry::Symmetry,
+};
+
+#[derive(Default)]
+pub struct PlacePlugin;
+
+impl Plugin for PlacePlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_state::<EditorMode>()
+      .init_resource::<CurrentBlock>()
+      .init_resource::<CursorCell>()
+      .init_resource::<PlaceDrag>()
+      .init_resource::<GhostMaterials>()
+      .add_systems(OnEnter(EditorMode::Place), spawn_ghost)
+      .add_systems(Update, toggle_editor_mode.run_if(no_egui_keyboard_focus))
+      .add_systems(
+        Update,
+        (
+          change_current_block.run_if(no_egui_keyboard_focus),
+          rotate_current_block.run_if(no_egui_keyboard_focus),
+          update_cursor_cell,
+          update_ghost,
+          place_blocks,
+          draw_place_drag,
+        )
+          .chain()
+          .run_if(in_state(EditorMode::Place)),
+      );
+  }
+}
+
+/// What clicking in the viewport does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, States)]
+pub enum EditorMode {
+  /// Clicking selects blocks.
+  #[default]
+  Select,
+  /// Clicking places the [`CurrentBlock`].
+  Place,
+}
+
+/// The block that is placed in [`EditorMode::Place`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct CurrentBlock {
+  /// The block type, below [`BLOCK_TYPE_COUNT`].
+  pub type_id: u8,
+  /// The palette index used for every color slot of the block.
+  pub color_index: u8,
+  pub material: u8,
+}
+
+impl CurrentBlock {
+  /// Sets the block type, clamped to the valid range.
+  pub fn set_type(&mut self, type_id: u8) {
+    self.type_id = type_id.min(BLOCK_TYPE_COUNT - 1);
+  }
+
+  /// Returns a single-cell block of this kind at `cell`, in blocks.
+  pub fn block_at(&self, cell: IVec3) -> Block {
+    let (frame, pos) =
+      world_to_frame_pos(cell.as_vec3() + Vec3::splat(0.5), FRAME_SIZE);
+    let color = self.color_index;
+
+    Block {
+      colors: (color, color, color, color, color, color, color),
+      extra: 0,
+      frame_x: frame.x as i8,
+      frame_y: frame.y as i8,
+      frame_z: frame.z as i8,
+      material: self.material,
+      pos_x: pos.x as u8,
+      pos_y: pos.y as u8,
+      pos_z: pos.z as u8,
+      size_x: 0,
+      size_y: 0,
+      size_z: 0,
+      r#type: self.type_id,
+    }
+  }
+}
+
+/// The grid cell under the cursor where a block would be placed, in blocks.
+///
+/// This is next to the face of the block under the cursor, or on the ground
+/// plane if there is none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct CursorCell {
+  pub cell: Option<IVec3>,
+  /// Whether a block can be placed in the cell, according to [`can_place`].
+  pub valid: bool,
+}
+
+/// Returns whether a block can be placed in `cell`, which needs to be free of
+/// `blocks` and within one of `frames`.
+pub fn can_place<'a>(
+  cell: IVec3,
+  mut blocks: impl Iterator<Item = &'a Block>,
+  frames: &[Frame],
+) -> bool {
+  let frame = cell.div_euclid(IVec3::splat(FRAME_SIZE as i32));
+  frames.iter().any(|other| other.coords() == frame)
+    && !blocks.any(|block| block.contains_cell(cell))
+}
+
+/// The translucent materials of the ghost previewing the [`CurrentBlock`].
+#[derive(Debug, Clone, Resource)]
+pub struct GhostMaterials {
+  pub valid: Handle<StandardMaterial>,
+  /// Used when the block can't be placed in the [`CursorCell`].
+  pub invalid: Handle<StandardMaterial>,
+}
+
+impl FromWorld for GhostMaterials {
+  fn from_world(world: &mut World) -> Self {
+    let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+    let mut ghost = |color| {
+      materials.add(StandardMaterial {
+        base_color: color,
+        alpha_mode: AlphaMode::Blend,
+        ..Default::default()
+      })
+    };
+
+    Self {
+      valid: ghost(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+      invalid: ghost(Color::srgba(1.0, 0.0, 0.0, 0.5)),
+    }
+  }
+}
+
+/// Marks the preview of the [`CurrentBlock`] at the [`CursorCell`].
+#[derive(Component)]
+pub struct Ghost;
+
+pub fn toggle_editor_mode(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mode: Res<State<EditorMode>>,
+  mut next_mode: ResMut<NextState<EditorMode>>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::KeyP) {
+    next_mode.set(match mode.get() {
+      EditorMode::Select => EditorMode::Place,
+      EditorMode::Place => EditorMode::Select,
+    });
+  }
+}
+
+pub fn change_current_block(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut current_block: ResMut<CurrentBlock>,
+) {
+  const DIGITS: [KeyCode; 10] = [
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+  ];
+
+  let type_id = current_block.type_id;
+
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::BracketLeft) {
+    current_block.set_type(type_id.saturating_sub(1));
+  }
+
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::BracketRight) {
+    current_block.set_type(type_id.saturating_add(1));
+  }
+
+  // TODO: Make controls configurable.
+  if let Some(digit) = DIGITS.iter().position(|key| keycode.just_pressed(*key))
+  {
+    current_block.set_type(digit as u8);
+  }
+}
+
+/// Rotates the [`CurrentBlock`] by 90° about the grid axis closest to the view
+/// direction, clockwise on screen, or counterclockwise with Shift held.
+pub fn rotate_current_block(
+  keycode: Res<ButtonInput<KeyCode>>,
+  camera: Single<&GlobalTransform, With<Camera3d>>,
+  mut current_block: ResMut<CurrentBlock>,
+) {
+  // TODO: Make controls configurable.
+  // Control+R reloads the blueprint instead.
+  if !keycode.just_pressed(KeyCode::KeyR)
+    || keycode.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+  {
+    return;
+  }
+
+  let forward = camera.forward();
+  let axis = GridAxis::dominant(*forward);
+
+  // A counterclockwise turn about the axis looks clockwise from behind it.
+  let mut turns = if forward.dot(axis.unit().as_vec3()) > 0.0 {
+    1
+  } else {
+    -1
+  };
+  // TODO: Make controls configurable.
+  if keycode.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+    turns = -turns;
+  }
+
+  match rotate_block_type(current_block.type_id, axis, turns) {
+    Some(type_id) => current_block.type_id = type_id,
+    None => info!(
+      "Block type {} can't be rotated, as its shape is unknown",
+      current_block.type_id
+    ),
+  }
+}
+
+pub fn update_cursor_cell(
+  window: Single<&Window, With<PrimaryWindow>>,
+  camera: Single<(&Camera, &GlobalTransform), With<Camera3d>>,
+  blocks: Query<&BlockData>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut cursor_cell: ResMut<CursorCell>,
+) {
+  let (camera, camera_transform) = *camera;
+  let Some(ray) = window
+    .cursor_position()
+    .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok())
+  else {
+    cursor_cell.set_if_neq(CursorCell::default());
+    return;
+  };
+
+  let hit = blocks
+    .iter()
+    .filter_map(|block| {
+      block
+        .raycast(ray.origin, *ray.direction, FRAME_SIZE)
+        .map(|distance| (block, distance))
+    })
+    .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+  let cell = if let Some((block, distance)) = hit {
+    // Step out of the face that was hit, into the neighbouring cell.
+    let point = ray.get_point(distance);
+    let (min, max) = block.aabb(FRAME_SIZE);
+    let normal = face_normal(point, min, max);
+    Some((point + normal * 0.5).floor().as_ivec3())
+  } else {
+    ray
+      .intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))
+      .map(|distance| ray.get_point(distance).floor().as_ivec3().with_y(0))
+  };
+
+  let frames = blueprint_frames(&blueprints, &blueprint);
+  let valid = cell.is_some_and(|cell| {
+    can_place(cell, blocks.iter().map(|block| &block.0), frames)
+  });
+
+  cursor_cell.set_if_neq(CursorCell { cell, valid });
+}
+
+/// Returns the outward normal of the face of the box from `min` to `max` that
+/// `point` is closest to.
+fn face_normal(point: Vec3, min: Vec3, max: Vec3) -> Vec3 {
+  let faces = [
+    (point.x - min.x, Vec3::NEG_X),
+    (max.x - point.x, Vec3::X),
+    (point.y - min.y, Vec3::NEG_Y),
+    (max.y - point.y, Vec3::Y),
+    (point.z - min.z, Vec3::NEG_Z),
+    (max.z - point.z, Vec3::Z),
+  ];
+
+  faces
+    .into_iter()
+    .min_by(|(a, _), (b, _)| a.abs().total_cmp(&b.abs()))
+    .map(|(_, normal)| normal)
+    .unwrap_or(Vec3::Y)
+}
+
+pub fn spawn_ghost(
+  mut commands: Commands,
+  common_assets: Res<CommonAssets>,
+  ghost_materials: Res<GhostMaterials>,
+  current_block: Res<CurrentBlock>,
+) {
+  commands.spawn((
+    DespawnOnExit(EditorMode::Place),
+    Ghost,
+    Mesh3d(common_assets.block(current_block.type_id)),
+    MeshMaterial3d(ghost_materials.valid.clone()),
+    Transform::default(),
+    Visibility::Hidden,
+  ));
+}
+
+pub fn update_ghost(
+  common_assets: Res<CommonAssets>,
+  ghost_materials: Res<GhostMaterials>,
+  current_block: Res<CurrentBlock>,
+  cursor_cell: Res<CursorCell>,
+  ghost: Single<
+    (
+      &mut Mesh3d,
+      &mut MeshMaterial3d<StandardMaterial>,
+      &mut Transform,
+      &mut Visibility,
+    ),
+    With<Ghost>,
+  >,
+) {
+  let (mut mesh, mut material, mut transform, mut visibility) =
+    ghost.into_inner();
+
+  if current_block.is_changed() {
+    mesh.0 = common_assets.block(current_block.type_id);
+  }
+
+  let ghost_material = if cursor_cell.valid {
+    &ghost_materials.valid
+  } else {
+    &ghost_materials.invalid
+  };
+  if material.0 != *ghost_material {
+    material.0 = ghost_material.clone();
+  }
+
+  match cursor_cell.cell {
+    Some(cell) => {
+      *transform = current_block.block_at(cell).world_transform();
+      *visibility = Visibility::Inherited;
+    }
+    None => *visibility = Visibility::Hidden,
+  }
+}
+
+/// Where a drag placing blocks started, in blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct PlaceDrag {
+  pub start: Option<IVec3>,
+}
+
+/// Which cells a drag from one cell to another fills.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlaceShape {
+  /// A row along the axis moved along the most.
+  Line,
+  /// The box with the two cells as opposite corners.
+  Box(FillPattern),
+}
+
+impl PlaceShape {
+  /// Returns the shape chosen by the held modifier keys.
+  pub fn from_keys(keycode: &ButtonInput<KeyCode>) -> Self {
+    // TODO: Make controls configurable.
+    if keycode.pressed(KeyCode::ControlLeft) {
+      // TODO: Make controls configurable.
+      if keycode.pressed(KeyCode::AltLeft) {
+        Self::Box(FillPattern::Shell)
+      } else {
+        Self::Box(FillPattern::Solid)
+      }
+    } else {
+      Self::Line
+    }
+  }
+
+  /// Returns the minimum and maximum corners of the cells filled from `start`
+  /// to `end`.
+  pub fn bounds(self, start: IVec3, end: IVec3) -> (IVec3, IVec3) {
+    let end = match self {
+      Self::Line => line_cells(start, end).last().unwrap_or(start),
+      Self::Box(_) => clamp_drag_end(start, end),
+    };
+    (start.min(end), start.max(end))
+  }
+
+  /// Returns the cells filled from `start` to `end`.
+  pub fn cells(self, start: IVec3, end: IVec3) -> Vec<IVec3> {
+    match self {
+      Self::Line => line_cells(start, end).collect(),
+      Self::Box(fill) => box_cells(start, end, fill).collect(),
+    }
+  }
+}
+
+/// Places a block on click, or the [`PlaceShape`] of blocks from where the
+/// mouse was pressed to where it was released.
+///
+/// Cells that are occupied or outside of a frame are skipped. While
+/// [`Symmetry`] is enabled, the mirrored blocks are placed as well.
+#[allow(clippy::too_many_arguments)]
+pub fn place_blocks(
+  mut contexts: EguiContexts,
+  mouse_buttons: Res<ButtonInput<MouseButton>>,
+  keycode: Res<ButtonInput<KeyCode>>,
+  current_block: Res<CurrentBlock>,
+  cursor_cell: Res<CursorCell>,
+  symmetry: Res<Symmetry>,
+  blocks: Query<&BlockData>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut place_drag: ResMut<PlaceDrag>,
+  mut messages: MessageWriter<ActionMessage>,
+) -> Result {
+  // TODO: Make controls configurable.
+  if mouse_buttons.just_pressed(MouseButton::Left)
+    && !contexts.ctx_mut()?.is_pointer_over_area()
+  {
+    place_drag.start = cursor_cell.cell;
+  }
+
+  // TODO: Make controls configurable.
+  if !mouse_buttons.just_released(MouseButton::Left) {
+    return Ok(());
+  }
+
+  let (Some(start), Some(end)) = (place_drag.start.take(), cursor_cell.cell)
+  else {
+    return Ok(());
+  };
+  if clamp_drag_end(start, end) != end {
+    warn!("Only placing up to {MAX_DRAG_EXTENT} blocks along each axis");
+  }
+
+  let frames = blueprint_frames(&blueprints, &blueprint);
+  let plane = symmetry.active_plane(blueprints.get(blueprint.id()));
+  // A mirrored block can land on another block of the same drag, or on
+  // itself if it is on the plane.
+  let mut placed = HashSet::<IVec3>::default();
+  let actions = PlaceShape::from_keys(&keycode)
+    .cells(start, end)
+    .into_iter()
+    .map(|cell| current_block.block_at(cell))
+    .flat_map(|block| {
+      let mirrored = plane.map(|plane| plane.mirror(&block));
+      core::iter::once(block).chain(mirrored)
+    })
+    .filter(|block| {
+      placed.insert(block.min())
+        && can_place(block.min(), blocks.iter().map(|block| &block.0), frames)
+    })
+    .map(|block| Box::new(PlaceAction::new(block)) as BoxedAction)
+    .collect::<Vec<_>>();
+
+  if !actions.is_empty() {
+    messages.write(ActionMessage::Push(Box::new(CombinedAction(actions))));
+  }
+
+  Ok(())
+}
+
+/// Outlines the region a drag will fill before the mouse is released.
+pub fn draw_place_drag(
+  mut gizmos: Gizmos,
+  keycode: Res<ButtonInput<KeyCode>>,
+  place_drag: Res<PlaceDrag>,
+  cursor_cell: Res<CursorCell>,
+) {
+  let (Some(start), Some(end)) = (place_drag.start, cursor_cell.cell) else {
+    return;
+  };
+
+  let (min, max) = PlaceShape::from_keys(&keycode).bounds(start, end);
+  let (min, max) = (min.as_vec3(), (max + 1).as_vec3());
+  gizmos.cube(
+    Transform::from_translation((min + max) * 0.5).with_scale(max - min),
+    Color::WHITE,
+  );
+}
+
+/// The most cells a drag fills along each axis, so that a stray drag across
+/// the view can't place an enormous number of blocks.
+pub const MAX_DRAG_EXTENT: i32 = 4 * FRAME_SIZE as i32;
+
+/// Moves `end` towards `start` as far as needed for the cells between them to
+/// span at most [`MAX_DRAG_EXTENT`] along each axis.
+pub fn clamp_drag_end(start: IVec3, end: IVec3) -> IVec3 {
+  let reach = IVec3::splat(MAX_DRAG_EXTENT - 1);
+  start + (end - start).clamp(-reach, reach)
+}
+
+/// Returns the cells from `start` to `end`, inclusive, along the axis with
+/// the largest difference between them, up to [`MAX_DRAG_EXTENT`] of them.
+pub fn line_cells(start: IVec3, end: IVec3) -> impl Iterator<Item = IVec3> {
+  let end = clamp_drag_end(start, end);
+  let delta = end - start;
+  let abs = delta.abs();
+  let axis = if abs.x >= abs.y && abs.x >= abs.z {
+    IVec3::X
+  } else if abs.y >= abs.z {
+    IVec3::Y
+  } else {
+    IVec3::Z
+  };
+
+  let length = (delta * axis).element_sum();
+  let step = axis * length.signum();
+  (0..=length.abs()).map(move |i| start + step * i)
+}
+
+/// Returns the cells of the box with `a` and `b` as opposite corners, filled
+/// according to `fill`. The box can span several frames, but is cut off at
+/// [`MAX_DRAG_EXTENT`] cells from `a` along each axis.
+pub fn box_cells(
+  a: IVec3,
+  b: IVec3,
+  fill: FillPattern,
+) -> impl Iterator<Item = IVec3> {
+  let b = clamp_drag_end(a, b);
+  let (min, max) = (a.min(b), a.max(b));
+  let dims = (max - min + 1).as_uvec3();
+
+  (min.x..=max.x).flat_map(move |x| {
+    (min.y..=max.y).flat_map(move |y| {
+      (min.z..=max.z).filter_map(move |z| {
+        let cell = IVec3::new(x, y, z);
+        fill.contains((cell - min).as_uvec3(), dims).then_some(cell)
+      })
+    })
+  })
+}
+
+/// The frames of the loaded blueprint, or none if it isn't loaded.
+fn blueprint_frames<'a>(
+  blueprints: &'a Assets<Blueprint>,
+  blueprint: &LoadedBlueprint,
+) -> &'a [Frame] {
+  blueprints
+    .get(blueprint.id())
+    .map(|blueprint| blueprint.data.frames.as_slice())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn drags_are_capped_along_each_axis() {
+    let start = IVec3::new(5, -3, 0);
+    let far = start + IVec3::new(1000, -1000, 3);
+
+    let line = line_cells(start, far).collect::<Vec<_>>();
+    assert_eq!(line.len(), MAX_DRAG_EXTENT as usize);
+    assert_eq!(line.last(), Some(&(start + IVec3::X * 47)));
+
+    let cells = box_cells(start, far, FillPattern::Solid).count();
+    assert_eq!(cells, 48 * 48 * 4);
+    assert_eq!(
+      PlaceShape::Box(FillPattern::Shell).bounds(start, far),
+      (IVec3::new(5, -50, 0), IVec3::new(52, -3, 3)),
+    );
+    assert_eq!(line_cells(start, start + IVec3::Y * 3).count(), 4);
+  }
+}
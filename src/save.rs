@@ -0,0 +1,484 @@
+//! Writing the edited blueprint back to JSON.
+
+use std::{
+  fs::{self, File},
+  io::{BufWriter, Write},
+  path::{Path, PathBuf},
+  time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+  BlockData, BlockIndex,
+  action::{ActionHistory, ActionMessage, HistoryError},
+  blueprint::{Blueprint, BlueprintError, BlueprintState, LoadedBlueprint},
+  no_egui_keyboard_focus, setup_blueprint,
+};
+
+/// The folder asset paths are relative to.
+pub const ASSETS_DIR: &str = "assets";
+
+#[derive(Default)]
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<AutoSave>()
+      .init_resource::<AutoSaveTimer>()
+      .init_resource::<JsonFormat>()
+      .init_resource::<MaterialDensities>()
+      .init_resource::<FileWatch>()
+      .init_resource::<WatchedFile>()
+      .add_systems(Startup, report_backups)
+      .add_systems(
+        OnEnter(BlueprintState::Loaded),
+        load_history.after(setup_blueprint),
+      )
+      .add_systems(
+        Update,
+        (
+          save_blueprint.run_if(no_egui_keyboard_focus),
+          auto_save,
+          watch_blueprint_file,
+        ),
+      );
+  }
+}
+
+/// How blueprints are formatted when written as JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub enum JsonFormat {
+  /// Without any whitespace, as Archean writes blueprints.
+  #[default]
+  Compact,
+  /// Indented over many lines, which diffs cleanly in version control.
+  Pretty,
+}
+
+impl JsonFormat {
+  /// Every format, in the order they are listed in.
+  pub const ALL: [Self; 2] = [Self::Compact, Self::Pretty];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Compact => "Compact",
+      Self::Pretty => "Pretty",
+    }
+  }
+}
+
+/// The density of each block material, by index, which the mass of saved
+/// blueprints is computed from.
+///
+/// There are none by default, as the densities the game uses aren't known, so
+/// saved blueprints keep the mass they were loaded with. Once any are
+/// inserted, materials past the end have a density of one.
+#[derive(Debug, Clone, PartialEq, Default, Resource, Deref, DerefMut)]
+pub struct MaterialDensities(pub Vec<f32>);
+
+/// Settings for periodically writing unsaved changes to a backup file next
+/// to the blueprint, which is left untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct AutoSave {
+  pub enabled: bool,
+  pub interval: Duration,
+}
+
+impl Default for AutoSave {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      interval: Duration::from_secs(60),
+    }
+  }
+}
+
+/// Counts down to the next [`AutoSave`].
+#[derive(Debug, Clone, Deref, DerefMut, Resource)]
+pub struct AutoSaveTimer(pub Timer);
+
+impl Default for AutoSaveTimer {
+  fn default() -> Self {
+    Self(Timer::new(
+      AutoSave::default().interval,
+      TimerMode::Repeating,
+    ))
+  }
+}
+
+/// Settings for watching the file of the active blueprint, to reload it when
+/// another program changes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct FileWatch {
+  pub enabled: bool,
+  /// How often the file is checked.
+  pub interval: Duration,
+}
+
+impl Default for FileWatch {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      interval: Duration::from_secs(1),
+    }
+  }
+}
+
+/// The file of the active blueprint and when it was last modified, as far as
+/// the editor knows, whether by another program or by saving.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct WatchedFile {
+  pub path: Option<PathBuf>,
+  pub modified: Option<SystemTime>,
+  /// Whether the file changed on disk while there were unsaved changes, so it
+  /// wasn't reloaded.
+  pub outdated: bool,
+}
+
+/// Returns when the file at `path` was last modified, if that can be read.
+fn modified_time(path: impl AsRef<Path>) -> Option<SystemTime> {
+  fs::metadata(path)
+    .and_then(|metadata| metadata.modified())
+    .ok()
+}
+
+/// Rebuilds `original` with the blocks as they are now, in index order, and
+/// drops references to missing components and updates the bounds, so the
+/// result is consistent.
+///
+/// The mass is recomputed with the given `material_densities`, unless there
+/// are none, in which case the mass `original` was loaded with is kept.
+pub fn reconstruct_blueprint<'a>(
+  original: &Blueprint,
+  blocks: impl IntoIterator<Item = (&'a BlockIndex, &'a BlockData)>,
+  material_densities: &[f32],
+) -> Blueprint {
+  let mut blocks = blocks.into_iter().collect::<Vec<_>>();
+  blocks.sort_by_key(|(index, _)| **index);
+
+  let mut blueprint = original.clone();
+  blueprint.data.blocks = blocks
+    .into_iter()
+    .map(|(_, block)| block.0.clone())
+    .collect();
+
+  let removed = blueprint.data.compact();
+  if removed > 0 {
+    warn!("Dropped {removed} pipes and composite builds of missing components");
+  }
+  blueprint.update_bounds();
+  if !material_densities.is_empty() {
+    blueprint.mass = blueprint.data.compute_mass(material_densities);
+  }
+  blueprint
+}
+
+/// Returns the path of the file an asset at `path` is loaded from.
+pub fn asset_file_path(path: impl AsRef<Path>) -> PathBuf {
+  Path::new(ASSETS_DIR).join(path)
+}
+
+/// Returns the path of the backup file of the blueprint at `path`.
+pub fn backup_path(path: impl AsRef<Path>) -> PathBuf {
+  path.as_ref().with_extension("backup.json")
+}
+
+/// Returns the path of the file the action history of the blueprint at `path`
+/// is saved to.
+pub fn history_path(path: impl AsRef<Path>) -> PathBuf {
+  path.as_ref().with_extension("history.json")
+}
+
+/// Writes `blueprint` to `path` as JSON in the given `format`.
+///
+/// The JSON is written to a temporary file next to `path`, which then replaces
+/// it, so that `path` is never left half-written.
+pub fn write_blueprint(
+  blueprint: &Blueprint,
+  path: impl AsRef<Path>,
+  format: JsonFormat,
+) -> Result<(), BlueprintError> {
+  let path = path.as_ref();
+  let temporary = path.with_extension("json.tmp");
+
+  let mut writer = BufWriter::new(File::create(&temporary)?);
+  match format {
+    JsonFormat::Compact => serde_json::to_writer(&mut writer, blueprint)?,
+    JsonFormat::Pretty => serde_json::to_writer_pretty(&mut writer, blueprint)?,
+  }
+  writer.flush()?;
+  drop(writer);
+
+  std::fs::rename(&temporary, path)?;
+  Ok(())
+}
+
+/// Logs the backups that are newer than their blueprints, which are likely
+/// left over from a crash.
+pub fn report_backups(blueprint: Res<LoadedBlueprint>) {
+  for path in blueprint.handles().iter().filter_map(Handle::path) {
+    let path = asset_file_path(path.path());
+    let backup = backup_path(&path);
+
+    let modified = |path: &Path| {
+      std::fs::metadata(path).and_then(|metadata| metadata.modified())
+    };
+    let is_newer = match (modified(&backup), modified(&path)) {
+      (Ok(backup), Ok(original)) => backup > original,
+      (Ok(_), Err(_)) => true,
+      _ => false,
+    };
+
+    if is_newer {
+      warn!(
+        "Found a backup newer than {}, which may hold unsaved changes: {}",
+        path.display(),
+        backup.display()
+      );
+    }
+  }
+}
+
+/// Writes the blueprint back to the file it was loaded from, and the action
+/// history next to it.
+#[allow(clippy::too_many_arguments)]
+pub fn save_blueprint(
+  mut commands: Commands,
+  keycode: Res<ButtonInput<KeyCode>>,
+  json_format: Res<JsonFormat>,
+  material_densities: Res<MaterialDensities>,
+  mut watched_file: ResMut<WatchedFile>,
+  mut action_history: ResMut<ActionHistory>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  blocks: Query<(&BlockIndex, &BlockData)>,
+) {
+  // TODO: Make controls configurable.
+  if !(keycode.pressed(KeyCode::ControlLeft)
+    && keycode.just_pressed(KeyCode::KeyS))
+  {
+    return;
+  }
+
+  let (Some(original), Some(path)) =
+    (blueprints.get(blueprint.id()), blueprint.path())
+  else {
+    warn!("Could not save the blueprint, as none is loaded from a file");
+    return;
+  };
+
+  let path = asset_file_path(path.path());
+  let blueprint = reconstruct_blueprint(original, blocks, &material_densities);
+  match write_blueprint(&blueprint, &path, *json_format) {
+    Ok(()) => {
+      action_history.mark_clean();
+      info!("Saved the blueprint to {}", path.display());
+      // The editor made this change, so it needn't be reloaded.
+      watched_file.modified = modified_time(&path);
+      watched_file.path = Some(path.clone());
+      watched_file.outdated = false;
+
+      let history = history_path(&path);
+      commands.queue(move |world: &mut World| {
+        world.resource_scope(|world, action_history: Mut<ActionHistory>| {
+          if let Err(error) = action_history.save_to(world, &history) {
+            error!("Could not save the history: {error}");
+          }
+        });
+      });
+    }
+    Err(error) => error!("Could not save the blueprint: {error}"),
+  }
+}
+
+/// Restores the action history saved next to the active blueprint, if there
+/// is one and it was saved for the blocks as they are now.
+pub fn load_history(world: &mut World) {
+  let Some(path) = world
+    .resource::<LoadedBlueprint>()
+    .path()
+    .map(|path| history_path(asset_file_path(path.path())))
+  else {
+    return;
+  };
+  if !path.exists() {
+    return;
+  }
+
+  world.resource_scope(|world, mut action_history: Mut<ActionHistory>| {
+    match action_history.load_from(world, &path) {
+      Ok(()) => info!("Restored the history from {}", path.display()),
+      Err(HistoryError::Stale) => info!(
+        "Skipped the history in {}, as it is for an older blueprint",
+        path.display()
+      ),
+      Err(error) => warn!("Could not restore the history: {error}"),
+    }
+  });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn auto_save(
+  time: Res<Time>,
+  auto_save: Res<AutoSave>,
+  json_format: Res<JsonFormat>,
+  material_densities: Res<MaterialDensities>,
+  mut timer: ResMut<AutoSaveTimer>,
+  action_history: Res<ActionHistory>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  blocks: Query<(&BlockIndex, &BlockData)>,
+) {
+  if !auto_save.enabled {
+    return;
+  }
+
+  if timer.duration() != auto_save.interval {
+    timer.set_duration(auto_save.interval);
+    timer.reset();
+  }
+
+  if !timer.tick(time.delta()).just_finished() || !action_history.is_dirty() {
+    return;
+  }
+
+  let (Some(original), Some(path)) =
+    (blueprints.get(blueprint.id()), blueprint.path())
+  else {
+    return;
+  };
+
+  let backup = backup_path(asset_file_path(path.path()));
+  let blueprint = reconstruct_blueprint(original, blocks, &material_densities);
+  match write_blueprint(&blueprint, &backup, *json_format) {
+    Ok(()) => info!("Saved a backup to {}", backup.display()),
+    Err(error) => error!("Could not save a backup: {error}"),
+  }
+}
+
+/// Reloads the active blueprint when its file changes on disk, which clears
+/// the history as reloading by hand does.
+///
+/// If there are unsaved changes, the file isn't reloaded over them, and is
+/// marked as [`WatchedFile::outdated`] instead.
+#[allow(clippy::too_many_arguments)]
+pub fn watch_blueprint_file(
+  time: Res<Time>,
+  file_watch: Res<FileWatch>,
+  mut watched_file: ResMut<WatchedFile>,
+  mut since_check: Local<Duration>,
+  action_history: Res<ActionHistory>,
+  mut messages: MessageWriter<ActionMessage>,
+  asset_server: Res<AssetServer>,
+  blueprint: Res<LoadedBlueprint>,
+) {
+  if !file_watch.enabled {
+    return;
+  }
+
+  *since_check += time.delta();
+  if *since_check < file_watch.interval {
+    return;
+  }
+  *since_check = Duration::ZERO;
+
+  let Some(asset_path) = blueprint.path() else {
+    return;
+  };
+  let path = asset_file_path(asset_path.path());
+  let Some(modified) = modified_time(&path) else {
+    return;
+  };
+
+  // Switching to another blueprint only starts watching its file.
+  if watched_file.path.as_ref() != Some(&path) {
+    *watched_file = WatchedFile {
+      path: Some(path),
+      modified: Some(modified),
+      outdated: false,
+    };
+    return;
+  }
+
+  if watched_file.modified == Some(modified) {
+    return;
+  }
+  watched_file.modified = Some(modified);
+
+  if action_history.is_dirty() {
+    warn!(
+      "{} changed on disk, but isn't reloaded over unsaved changes",
+      path.display()
+    );
+    watched_file.outdated = true;
+    return;
+  }
+
+  info!("{} changed on disk, so it is reloaded", path.display());
+  messages.write(ActionMessage::Clear);
+  asset_server.reload(asset_path);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::blueprint::FillPattern;
+
+  fn spawned(blueprint: &Blueprint) -> Vec<(BlockIndex, BlockData)> {
+    blueprint
+      .data
+      .blocks
+      .iter()
+      .enumerate()
+      .map(|(i, block)| (BlockIndex(i), BlockData(block.clone())))
+      .collect()
+  }
+
+  #[test]
+  fn reconstruct_blueprint_keeps_mass_without_densities() {
+    let mut original =
+      Blueprint::synthetic(UVec3::new(2, 1, 1), FillPattern::Solid);
+    original.mass = 1234.5;
+    let blocks = spawned(&original);
+    let blocks = blocks.iter().map(|(index, block)| (index, block));
+
+    let blueprint = reconstruct_blueprint(&original, blocks, &[]);
+    assert_eq!(blueprint.mass, 1234.5);
+  }
+
+  #[test]
+  fn reconstruct_blueprint_recomputes_mass_with_densities() {
+    let mut original =
+      Blueprint::synthetic(UVec3::new(2, 1, 1), FillPattern::Solid);
+    original.mass = 1234.5;
+    let blocks = spawned(&original);
+    let blocks = blocks.iter().map(|(index, block)| (index, block));
+
+    let blueprint = reconstruct_blueprint(&original, blocks, &[3.0]);
+    assert_eq!(blueprint.mass, 6.0);
+  }
+
+  #[test]
+  fn write_blueprint_round_trips_in_each_format() {
+    let blueprint =
+      Blueprint::synthetic(UVec3::new(2, 2, 2), FillPattern::Checker);
+    let expected = serde_json::to_value(&blueprint).unwrap();
+
+    for format in JsonFormat::ALL {
+      let path = std::env::temp_dir().join(format!(
+        "archean-editor-{}-{}.json",
+        format.name(),
+        std::process::id()
+      ));
+      write_blueprint(&blueprint, &path, format).unwrap();
+      let json = std::fs::read_to_string(&path).unwrap();
+      std::fs::remove_file(&path).unwrap();
+
+      assert_eq!(json.contains('\n'), format == JsonFormat::Pretty);
+      let read = serde_json::from_str::<Blueprint>(&json).unwrap();
+      assert_eq!(serde_json::to_value(&read).unwrap(), expected);
+    }
+  }
+}
@@ -0,0 +1,243 @@
+//! Selecting many blocks at once, by dragging a box over the view or all of
+//! them together.
+
+use bevy::{
+  picking::{hover::HoverMap, pointer::PointerId},
+  prelude::*,
+  window::PrimaryWindow,
+};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+use crate::{
+  BlockData, Locked, Selected, SelectionControls,
+  action::{
+    ActionMessage, BoxedAction, CombinedAction, SelectionAction,
+    consume_actions_messages,
+  },
+  no_egui_keyboard_focus,
+  place::EditorMode,
+};
+
+/// Drags shorter than this, in logical pixels, are clicks rather than boxes.
+const MIN_DRAG: f32 = 4.0;
+
+#[derive(Default)]
+pub struct SelectPlugin;
+
+impl Plugin for SelectPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_message::<SelectionChanged>()
+      .init_resource::<BoxSelect>()
+      .add_systems(
+        Update,
+        (
+          (select_all, invert_selection).run_if(no_egui_keyboard_focus),
+          box_select.run_if(in_state(EditorMode::Select)),
+        ),
+      )
+      .add_systems(
+        PostUpdate,
+        write_selection_changed.after(consume_actions_messages),
+      )
+      .add_systems(EguiPrimaryContextPass, draw_box_select);
+  }
+}
+
+/// A [`Message`] written at most once a frame whenever blocks are selected or
+/// deselected, so that a [`CombinedAction`] changing many of them at once
+/// only causes one.
+#[derive(Debug, Clone, PartialEq, Eq, Message)]
+pub struct SelectionChanged {
+  /// Every entity that is selected now.
+  pub selected: Vec<Entity>,
+}
+
+/// Writes a [`SelectionChanged`] if any entity was selected or deselected
+/// since the last time this ran.
+pub fn write_selection_changed(
+  added: Query<(), Added<Selected>>,
+  mut removed: RemovedComponents<Selected>,
+  selected: Query<Entity, With<Selected>>,
+  mut messages: MessageWriter<SelectionChanged>,
+) {
+  // Drain the removals every time, so that old ones don't count next frame.
+  let removed = removed.read().count() > 0;
+  if added.is_empty() && !removed {
+    return;
+  }
+
+  messages.write(SelectionChanged {
+    selected: selected.iter().collect(),
+  });
+}
+
+/// A box being dragged over the view to select the blocks inside it.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Resource)]
+pub struct BoxSelect {
+  /// Where the drag started, in logical window coordinates, while there is
+  /// one.
+  pub start: Option<Vec2>,
+}
+
+/// Returns the mouse button a pointer button is pressed with.
+fn mouse_button(button: PointerButton) -> MouseButton {
+  match button {
+    PointerButton::Primary => MouseButton::Left,
+    PointerButton::Secondary => MouseButton::Right,
+    PointerButton::Middle => MouseButton::Middle,
+  }
+}
+
+/// Starts a box when the select button is pressed over empty space, and on
+/// release selects every unlocked block whose center is inside it.
+///
+/// The box replaces the selection, unless one of the additive modifiers of
+/// the [`SelectionControls`] is held, in which case it adds to it.
+#[allow(clippy::too_many_arguments)]
+pub fn box_select(
+  mut contexts: EguiContexts,
+  window: Single<&Window, With<PrimaryWindow>>,
+  camera: Single<(&Camera, &GlobalTransform), With<Camera3d>>,
+  mouse_buttons: Res<ButtonInput<MouseButton>>,
+  keycode: Res<ButtonInput<KeyCode>>,
+  controls: Res<SelectionControls>,
+  hover_map: Res<HoverMap>,
+  blocks: Query<(Entity, &GlobalTransform, Has<Selected>, Has<Locked>)>,
+  block_data: Query<(), With<BlockData>>,
+  mut box_select: ResMut<BoxSelect>,
+  mut messages: MessageWriter<ActionMessage>,
+) -> Result {
+  let button = mouse_button(controls.select);
+  if mouse_buttons.just_pressed(button)
+    && !contexts.ctx_mut()?.is_pointer_over_area()
+  {
+    // Pressing on a block selects it instead.
+    let over_block = hover_map.get(&PointerId::Mouse).is_some_and(|hovered| {
+      hovered.keys().any(|entity| block_data.contains(*entity))
+    });
+    if !over_block {
+      box_select.start = window.cursor_position();
+    }
+  }
+
+  if !mouse_buttons.just_released(button) {
+    return Ok(());
+  }
+
+  let (Some(start), Some(end)) =
+    (box_select.start.take(), window.cursor_position())
+  else {
+    return Ok(());
+  };
+
+  let rect = Rect::from_corners(start, end);
+  if rect.width() < MIN_DRAG && rect.height() < MIN_DRAG {
+    return Ok(());
+  }
+
+  let additive =
+    keycode.any_pressed(controls.additive_modifiers.iter().copied());
+  let (camera, camera_transform) = *camera;
+  let actions = blocks
+    .iter()
+    .filter(|(entity, ..)| block_data.contains(*entity))
+    .filter_map(|(entity, transform, selected, locked)| {
+      let inside = !locked
+        && camera
+          .world_to_viewport(camera_transform, transform.translation())
+          .is_ok_and(|point| rect.contains(point));
+      // Selection actions toggle, so only push them for blocks that change.
+      let toggle = if additive {
+        inside && !selected
+      } else {
+        inside != selected
+      };
+      toggle.then(|| Box::new(SelectionAction(entity)) as BoxedAction)
+    })
+    .collect::<CombinedAction>();
+
+  if !actions.is_empty() {
+    messages.write(ActionMessage::Push(Box::new(actions)));
+  }
+
+  Ok(())
+}
+
+/// Selects every unlocked block, or deselects every selected one.
+#[allow(clippy::type_complexity)]
+pub fn select_all(
+  keycode: Res<ButtonInput<KeyCode>>,
+  blocks: Query<(Entity, Has<Selected>), (With<BlockData>, Without<Locked>)>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  let select = if keycode.pressed(KeyCode::ControlLeft)
+    && keycode.just_pressed(KeyCode::KeyA)
+  {
+    true
+  } else if keycode.just_pressed(KeyCode::Escape) {
+    false
+  } else {
+    return;
+  };
+
+  // Selection actions toggle, so only push them for blocks that change.
+  let actions = blocks
+    .iter()
+    .filter(|(_, selected)| *selected != select)
+    .map(|(entity, _)| Box::new(SelectionAction(entity)) as BoxedAction)
+    .collect::<CombinedAction>();
+
+  if !actions.is_empty() {
+    messages.write(ActionMessage::Push(Box::new(actions)));
+  }
+}
+
+/// Selects every unlocked block that isn't selected, and deselects every one
+/// that is.
+pub fn invert_selection(
+  keycode: Res<ButtonInput<KeyCode>>,
+  blocks: Query<Entity, (With<BlockData>, Without<Locked>)>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  if !(keycode.pressed(KeyCode::ControlLeft)
+    && keycode.just_pressed(KeyCode::KeyI))
+    || blocks.is_empty()
+  {
+    return;
+  }
+
+  messages.write(ActionMessage::Push(Box::new(
+    blocks
+      .iter()
+      .map(|entity| Box::new(SelectionAction(entity)) as BoxedAction)
+      .collect::<CombinedAction>(),
+  )));
+}
+
+/// Outlines the box being dragged, if there is one.
+pub fn draw_box_select(
+  mut contexts: EguiContexts,
+  window: Single<&Window, With<PrimaryWindow>>,
+  box_select: Res<BoxSelect>,
+) -> Result {
+  let (Some(start), Some(end)) = (box_select.start, window.cursor_position())
+  else {
+    return Ok(());
+  };
+
+  let ctx = contexts.ctx_mut()?;
+  ctx.layer_painter(egui::LayerId::background()).rect_stroke(
+    egui::Rect::from_two_pos(
+      egui::pos2(start.x, start.y),
+      egui::pos2(end.x, end.y),
+    ),
+    0.0,
+    egui::Stroke::new(1.0, egui::Color32::WHITE),
+    egui::StrokeKind::Middle,
+  );
+
+  Ok(())
+}
@@ -0,0 +1,218 @@
+//! The shapes of block types, and how they map onto each other when rotated.
+//!
+//! Every block mesh is the convex hull of some of the corners of the unit
+//! cube, and no two block types cover the same corners. This lets a rotation
+//! be applied to the corners of a block type, and the rotated type be found
+//! by looking the result up.
+
+use core::ops::RangeInclusive;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The corners of the unit cube covered by the mesh of each block type, as
+/// bits indexed by `(x > 0) * 4 + (y > 0) * 2 + (z > 0)`.
+pub const CORNER_MASKS: [u8; 53] = [
+  255, 119, 187, 238, 221, 95, 63, 175, 207, 245, 252, 250, 243, 113, 178, 232,
+  212, 23, 43, 142, 77, 115, 186, 236, 213, 31, 47, 143, 79, 205, 174, 59, 87,
+  244, 248, 242, 241, 55, 93, 206, 171, 220, 117, 179, 234, 127, 191, 239, 223,
+  253, 254, 251, 247,
+];
+
+/// An axis of the block grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GridAxis {
+  X,
+  Y,
+  Z,
+}
+
+impl GridAxis {
+  /// Returns the axis `direction` is most aligned with.
+  pub fn dominant(direction: Vec3) -> Self {
+    let abs = direction.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+      Self::X
+    } else if abs.y >= abs.z {
+      Self::Y
+    } else {
+      Self::Z
+    }
+  }
+
+  /// The unit vector along the positive axis.
+  pub fn unit(self) -> IVec3 {
+    match self {
+      Self::X => IVec3::X,
+      Self::Y => IVec3::Y,
+      Self::Z => IVec3::Z,
+    }
+  }
+
+  /// Rotates `v` by 90° counterclockwise about the axis, looking from the
+  /// positive end of the axis towards the origin.
+  pub fn quarter_turn(self, v: IVec3) -> IVec3 {
+    let IVec3 { x, y, z } = v;
+    match self {
+      Self::X => IVec3::new(x, -z, y),
+      Self::Y => IVec3::new(z, y, -x),
+      Self::Z => IVec3::new(-y, x, z),
+    }
+  }
+}
+
+/// Returns the corner an `index` into a corner mask refers to, with each
+/// coordinate being -1 or 1.
+fn corner(index: u8) -> IVec3 {
+  let sign = |bit| if index & bit != 0 { 1 } else { -1 };
+  IVec3::new(sign(4), sign(2), sign(1))
+}
+
+/// Returns the index into a corner mask of `corner`.
+fn corner_index(corner: IVec3) -> u8 {
+  (corner.x > 0) as u8 * 4 + (corner.y > 0) as u8 * 2 + (corner.z > 0) as u8
+}
+
+/// Rotates a corner mask by 90° counterclockwise about `axis`, looking from
+/// the positive end of the axis towards the origin.
+pub fn rotate_mask(mask: u8, axis: GridAxis) -> u8 {
+  (0..8)
+    .filter(|index| mask & (1 << index) != 0)
+    .map(|index| 1 << corner_index(axis.quarter_turn(corner(index))))
+    .fold(0, |rotated, bit| rotated | bit)
+}
+
+/// Mirrors a corner mask across the plane perpendicular to `axis`.
+pub fn mirror_mask(mask: u8, axis: GridAxis) -> u8 {
+  let bit = match axis {
+    GridAxis::X => 4,
+    GridAxis::Y => 2,
+    GridAxis::Z => 1,
+  };
+  (0..8)
+    .filter(|index| mask & (1 << index) != 0)
+    .fold(0, |mirrored, index| mirrored | 1 << (index ^ bit))
+}
+
+/// Returns the block type with the given corner mask, if there is one.
+pub fn type_with_mask(mask: u8) -> Option<u8> {
+  CORNER_MASKS
+    .iter()
+    .position(|other| *other == mask)
+    .map(|type_id| type_id as u8)
+}
+
+/// Returns the block type `type_id` becomes after `turns` quarter turns
+/// counterclockwise about `axis`, or clockwise if `turns` is negative. The
+/// result is always in the same [`ShapeFamily`].
+///
+/// Returns [`None`] for block types whose shape isn't known.
+pub fn rotate_block_type(
+  type_id: u8,
+  axis: GridAxis,
+  turns: i32,
+) -> Option<u8> {
+  let mut mask = *CORNER_MASKS.get(type_id as usize)?;
+  for _ in 0..turns.rem_euclid(4) {
+    mask = rotate_mask(mask, axis);
+  }
+  type_with_mask(mask)
+}
+
+/// Returns the block type `type_id` becomes when mirrored across the plane
+/// perpendicular to `axis`. The result is always in the same [`ShapeFamily`].
+///
+/// Returns [`None`] for block types whose shape isn't known.
+pub fn mirror_block_type(type_id: u8, axis: GridAxis) -> Option<u8> {
+  let mask = *CORNER_MASKS.get(type_id as usize)?;
+  type_with_mask(mirror_mask(mask, axis))
+}
+
+/// A base shape, of which each block type is one orientation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShapeFamily {
+  /// All eight corners, in one orientation.
+  Cube,
+  /// Six corners, cut diagonally along an edge, in 12 orientations.
+  Slope,
+  /// Four corners, a tetrahedron in a corner of the cube, in 8 orientations.
+  Corner,
+  /// Five corners, a face and one corner opposite it, in 24 orientations.
+  SlopeCorner,
+  /// Seven corners, the cube with a corner cut off, in 8 orientations.
+  InvertedCorner,
+}
+
+impl ShapeFamily {
+  pub const ALL: [Self; 5] = [
+    Self::Cube,
+    Self::Slope,
+    Self::Corner,
+    Self::SlopeCorner,
+    Self::InvertedCorner,
+  ];
+
+  /// The block types of the family, in order of orientation.
+  pub fn type_ids(self) -> RangeInclusive<u8> {
+    match self {
+      Self::Cube => 0..=0,
+      Self::Slope => 1..=12,
+      Self::Corner => 13..=20,
+      Self::SlopeCorner => 21..=44,
+      Self::InvertedCorner => 45..=52,
+    }
+  }
+
+  /// The number of orientations the family has.
+  pub fn orientations(self) -> u8 {
+    let type_ids = self.type_ids();
+    type_ids.end() - type_ids.start() + 1
+  }
+}
+
+/// Returns the family and orientation of a block type, or [`None`] if its
+/// shape isn't known.
+pub fn block_shape(type_id: u8) -> Option<(ShapeFamily, u8)> {
+  ShapeFamily::ALL.into_iter().find_map(|family| {
+    let type_ids = family.type_ids();
+    type_ids
+      .contains(&type_id)
+      .then(|| (family, type_id - type_ids.start()))
+  })
+}
+
+/// Returns the block type of a family in an orientation, or [`None`] if the
+/// family has no such orientation.
+pub fn block_type(family: ShapeFamily, orientation: u8) -> Option<u8> {
+  (orientation < family.orientations())
+    .then(|| family.type_ids().start() + orientation)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mirror_block_type_mirrors_slope_and_corner() {
+    // Slope 1 is cut along X, so it only changes when mirrored across Y or Z.
+    assert_eq!(mirror_block_type(1, GridAxis::X), Some(1));
+    assert_eq!(mirror_block_type(1, GridAxis::Y), Some(4));
+    // Corner 13 fills the corner at +X -Y -Z, so mirroring it across Z fills
+    // the one at +X -Y +Z.
+    assert_eq!(mirror_block_type(13, GridAxis::Z), Some(14));
+  }
+
+  #[test]
+  fn mirror_block_type_twice_is_identity() {
+    for type_id in 0..CORNER_MASKS.len() as u8 {
+      for axis in [GridAxis::X, GridAxis::Y, GridAxis::Z] {
+        let mirrored = mirror_block_type(type_id, axis).unwrap();
+        assert_eq!(
+          block_shape(mirrored).map(|(family, _)| family),
+          block_shape(type_id).map(|(family, _)| family),
+        );
+        assert_eq!(mirror_block_type(mirrored, axis), Some(type_id));
+      }
+    }
+  }
+}
@@ -0,0 +1,135 @@
+//! Mirroring edits across a plane as they are made.
+
+use bevy::{color::palettes::css, prelude::*};
+
+use crate::{
+  Selected,
+  action::{ActionMessage, MirrorAction},
+  blueprint::{Block, Blueprint, LoadedBlueprint},
+  no_egui_keyboard_focus,
+  shape::GridAxis,
+};
+
+#[derive(Default)]
+pub struct SymmetryPlugin;
+
+impl Plugin for SymmetryPlugin {
+  fn build(&self, app: &mut App) {
+    app.init_resource::<Symmetry>().add_systems(
+      Update,
+      (
+        (toggle_symmetry, mirror_selection).run_if(no_egui_keyboard_focus),
+        draw_symmetry_plane,
+      ),
+    );
+  }
+}
+
+/// A plane perpendicular to an axis of the block grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymmetryPlane {
+  pub axis: GridAxis,
+  /// Twice the position of the plane along the axis, in blocks, so that the
+  /// plane can lie between cells as well as on their faces.
+  pub twice_offset: i32,
+}
+
+impl SymmetryPlane {
+  /// Returns `block` mirrored across the plane, or as it is if that would put
+  /// it in a frame out of range.
+  pub fn mirror(&self, block: &Block) -> Block {
+    let mut mirrored = block.clone();
+    mirrored.mirror(self.axis, self.twice_offset);
+    mirrored
+  }
+}
+
+/// Settings for mirroring placements and deletions across a plane as they are
+/// made, so that each edit is also made to its mirrored counterpart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct Symmetry {
+  pub enabled: bool,
+  /// The plane to mirror across, or [`None`] to use the blueprint's own.
+  pub plane: Option<SymmetryPlane>,
+}
+
+impl Symmetry {
+  /// Returns the plane edits are mirrored across, or [`None`] if mirroring is
+  /// disabled or there is no plane to mirror across.
+  pub fn active_plane(
+    &self,
+    blueprint: Option<&Blueprint>,
+  ) -> Option<SymmetryPlane> {
+    if !self.enabled {
+      return None;
+    }
+    self.plane.or_else(|| {
+      blueprint.and_then(|blueprint| blueprint.data.symmetry_plane())
+    })
+  }
+}
+
+pub fn toggle_symmetry(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut symmetry: ResMut<Symmetry>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::KeyM) && !keycode.pressed(KeyCode::ShiftLeft)
+  {
+    symmetry.enabled = !symmetry.enabled;
+  }
+}
+
+/// Mirrors the selected blocks across the symmetry plane, whether or not
+/// mirroring edits is enabled.
+pub fn mirror_selection(
+  keycode: Res<ButtonInput<KeyCode>>,
+  symmetry: Res<Symmetry>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  selected: Query<Entity, With<Selected>>,
+  mut messages: MessageWriter<ActionMessage>,
+) {
+  // TODO: Make controls configurable.
+  if !(keycode.pressed(KeyCode::ShiftLeft)
+    && keycode.just_pressed(KeyCode::KeyM))
+    || selected.is_empty()
+  {
+    return;
+  }
+
+  let Some(plane) = symmetry.plane.or_else(|| {
+    blueprints
+      .get(blueprint.id())
+      .and_then(|blueprint| blueprint.data.symmetry_plane())
+  }) else {
+    warn!("Could not mirror the selection, as there is no symmetry plane");
+    return;
+  };
+
+  messages.write(ActionMessage::Push(Box::new(MirrorAction {
+    entities: selected.iter().collect(),
+    plane,
+  })));
+}
+
+/// Outlines the plane edits are mirrored across, while there is one.
+pub fn draw_symmetry_plane(
+  mut gizmos: Gizmos,
+  symmetry: Res<Symmetry>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+) {
+  let Some(plane) = symmetry.active_plane(blueprints.get(blueprint.id()))
+  else {
+    return;
+  };
+
+  // A cube flattened along the axis outlines a square on the plane.
+  let unit = plane.axis.unit().as_vec3();
+  gizmos.cube(
+    Transform::from_translation(unit * plane.twice_offset as f32 * 0.5)
+      .with_scale((Vec3::ONE - unit) * 120.0),
+    Color::from(css::FUCHSIA),
+  );
+}
@@ -0,0 +1,443 @@
+//! The editor's egui windows.
+
+use core::time::Duration;
+
+use bevy::{
+  camera::{CameraOutputMode, visibility::RenderLayers},
+  prelude::*,
+  render::render_resource::BlendState,
+};
+use bevy_egui::prelude::*;
+
+use crate::{
+  BlockData, BlockIndex, PickingFilter, Selected,
+  action::{ActionHistory, ActionMessage, PaintMaterialAction},
+  blueprint::{Blueprint, LoadedBlueprint},
+  component::InspectedComponent,
+  overlay::{
+    ComponentLabels, CompositeBuildMarkers, OverflowHighlights, PickNormals,
+  },
+  place::{CurrentBlock, EditorMode},
+  save::{AutoSave, JsonFormat, WatchedFile},
+  view::{
+    BackFaces, EmptyFrames, FaceColors, FrameCubes, GroundGrid, OuterShell,
+    RenderStyle, SelectionWireframes,
+  },
+};
+
+#[derive(Default)]
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .insert_resource(EguiGlobalSettings {
+        auto_create_primary_context: false,
+        ..Default::default()
+      })
+      .add_systems(Startup, setup_ui)
+      .add_systems(
+        EguiPrimaryContextPass,
+        (
+          show_editor_ui,
+          show_settings_ui,
+          show_history_ui,
+          show_paint_ui,
+          show_inspector_ui,
+        ),
+      );
+  }
+}
+
+pub fn setup_ui(mut commands: Commands) {
+  commands.spawn((
+    Camera2d,
+    PrimaryEguiContext,
+    RenderLayers::none(),
+    Camera {
+      order: 1,
+      output_mode: CameraOutputMode::Write {
+        blend_state: Some(BlendState::ALPHA_BLENDING),
+        clear_color: ClearColorConfig::None,
+      },
+      clear_color: ClearColorConfig::Custom(Color::NONE),
+      ..Default::default()
+    },
+  ));
+}
+
+pub fn show_editor_ui(
+  mut contexts: EguiContexts,
+  mut action_history: ResMut<ActionHistory>,
+  mode: Res<State<EditorMode>>,
+  current_block: Res<CurrentBlock>,
+  watched_file: Res<WatchedFile>,
+) -> Result {
+  let ctx = contexts.ctx_mut()?;
+
+  // TODO: Read controls from wherever they end up being confugured.
+  egui::Window::new("Controls").show(ctx, |ui| {
+    ui.heading("Camera");
+    ui.label("<MiddleMouse> to rotate.");
+    ui.label("<Shift+MiddleMouse> to translate.");
+    ui.label("<F> to focus on the selection, or the whole blueprint.");
+    ui.label("<Home> to reset the camera.");
+    ui.label("<T> to toggle turning the camera like a turntable.");
+    ui.label("<Numpad7>, <Numpad1> or <Numpad3> for a top, front or side.");
+    ui.label("<Numpad5> to toggle between orthographic and perspective.");
+    ui.label("<V> to cycle the render style.");
+    ui.label("<O> to show only the outer shell of the blueprint.");
+    ui.label("<F3> to show render statistics.");
+
+    ui.separator();
+
+    ui.heading("History");
+    ui.label("<Control+Z> to undo.");
+    ui.label("<Control+Shift+Z> or <Control+Y> to redo.");
+    ui.checkbox(
+      &mut action_history.record_non_mutating,
+      "Record selection changes",
+    );
+
+    ui.separator();
+
+    ui.heading("Editing");
+    ui.label("<Delete> or <Backspace> to delete the selected blocks.");
+    ui.label("<Alt+X>, <Alt+Y> or <Alt+Z> to flip the selected blocks.");
+    ui.label("<L> to lock the selected blocks against edits.");
+    ui.label("<Shift+L> to unlock every locked block.");
+    ui.label("<Control+D> to clone the selected blocks beside them.");
+    ui.label("<Control+C> to copy the selected blocks.");
+    ui.label("<Control+V> to paste them at the cursor or camera target.");
+    ui.label("<Arrows>, <PageUp> or <PageDown> to move the selected blocks.");
+    ui.label("Hold <Shift> while moving them to move by a whole frame.");
+    ui.label("<M> to mirror placing and deleting across the symmetry plane.");
+    ui.label("<Shift+M> to mirror the selected blocks across the plane.");
+
+    ui.separator();
+
+    ui.heading("Placing");
+    ui.label("<P> to toggle place mode.");
+    ui.label("<PrimaryMouse> to place a block in place mode.");
+    ui.label("Drag <PrimaryMouse> to place a row of blocks.");
+    ui.label("Hold <Control> while dragging to fill a box.");
+    ui.label("Hold <Control+Alt> while dragging to fill a hollow box.");
+    ui.label("<[> and <]> to change the block type.");
+    ui.label("<R> or <Shift+R> to rotate the block type.");
+    ui.label("<0> to <9> to pick one of the first ten block types.");
+    if *mode.get() == EditorMode::Place {
+      ui.label(format!("Placing block type {}.", current_block.type_id));
+    }
+
+    ui.separator();
+
+    ui.heading("Selection");
+    ui.label("<PrimaryMouse> to select hovered block.");
+    ui.label("<Shift+PrimaryMouse> to add hovered block to selection.");
+    ui.label("Drag <PrimaryMouse> from empty space to select a box.");
+    ui.label("Hold <Shift> while dragging to add the box to selection.");
+    ui.label("<Alt+PrimaryMouse> to select every block of the hovered type.");
+    ui.label("<Control+A> to select every block.");
+    ui.label("<Escape> to clear the selection.");
+    ui.label("<Control+I> to invert the selection.");
+
+    ui.separator();
+
+    ui.heading("Blueprint");
+    ui.label("<Control+S> to save the blueprint file.");
+    ui.label("<Control+R> to reload the blueprint file.");
+    ui.label("<Control+E> to export the visible blocks as an OBJ file.");
+    ui.label("<Tab> to switch between open blueprints.");
+    if watched_file.outdated {
+      ui.colored_label(
+        egui::Color32::YELLOW,
+        "The file changed on disk, but wasn't reloaded over unsaved changes.",
+      );
+    }
+  });
+
+  Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn show_settings_ui(
+  mut contexts: EguiContexts,
+  mut picking_filter: ResMut<PickingFilter>,
+  mut component_labels: ResMut<ComponentLabels>,
+  mut composite_build_markers: ResMut<CompositeBuildMarkers>,
+  mut pick_normals: ResMut<PickNormals>,
+  mut overflow_highlights: ResMut<OverflowHighlights>,
+  mut render_style: ResMut<RenderStyle>,
+  mut selection_wireframes: ResMut<SelectionWireframes>,
+  mut face_colors: ResMut<FaceColors>,
+  mut empty_frames: ResMut<EmptyFrames>,
+  mut frame_cubes: ResMut<FrameCubes>,
+  mut back_faces: ResMut<BackFaces>,
+  mut outer_shell: ResMut<OuterShell>,
+  mut ground_grid: ResMut<GroundGrid>,
+  mut auto_save: ResMut<AutoSave>,
+  mut json_format: ResMut<JsonFormat>,
+) -> Result {
+  let ctx = contexts.ctx_mut()?;
+
+  egui::Window::new("Settings").show(ctx, |ui| {
+    ui.heading("View");
+    let mut style = *render_style;
+    ui.horizontal(|ui| {
+      for option in RenderStyle::ALL {
+        ui.selectable_value(&mut style, option, option.name());
+      }
+    });
+    render_style.set_if_neq(style);
+    let mut enabled = selection_wireframes.enabled;
+    ui.checkbox(&mut enabled, "Draw wireframes over the selection");
+    selection_wireframes.set_if_neq(SelectionWireframes { enabled });
+    let mut enabled = face_colors.enabled;
+    ui.checkbox(&mut enabled, "Color cube faces individually");
+    face_colors.set_if_neq(FaceColors { enabled });
+    let mut hidden = empty_frames.hidden;
+    ui.checkbox(&mut hidden, "Hide frames without blocks");
+    empty_frames.set_if_neq(EmptyFrames { hidden });
+    let mut visible = frame_cubes.visible;
+    ui.checkbox(&mut visible, "Show frame cubes");
+    frame_cubes.set_if_neq(FrameCubes { visible });
+    let mut visible = back_faces.visible;
+    ui.checkbox(&mut visible, "Show back faces");
+    back_faces.set_if_neq(BackFaces { visible });
+    let mut enabled = outer_shell.enabled;
+    ui.checkbox(&mut enabled, "Show only the outer shell");
+    outer_shell.set_if_neq(OuterShell { enabled });
+    let mut grid = *ground_grid;
+    ui.checkbox(&mut grid.visible, "Show the ground grid");
+    ui.add_enabled(
+      grid.visible,
+      egui::Slider::new(&mut grid.extent, 1..=64).text("Grid extent (frames)"),
+    );
+    ground_grid.set_if_neq(grid);
+    ui.checkbox(&mut component_labels.enabled, "Show component labels");
+    ui.checkbox(
+      &mut composite_build_markers.enabled,
+      "Show components hosting sub-builds",
+    );
+    ui.checkbox(
+      &mut overflow_highlights.enabled,
+      "Outline blocks reaching past their frame",
+    );
+
+    ui.separator();
+
+    ui.heading("Picking");
+    let mut filter = *picking_filter;
+    ui.checkbox(&mut filter.blocks_only, "Pick blocks only");
+    ui.add_enabled_ui(!filter.blocks_only, |ui| {
+      ui.checkbox(&mut filter.blocks, "Pick blocks");
+      ui.checkbox(&mut filter.frames, "Pick frames");
+      ui.checkbox(&mut filter.components, "Pick components");
+    });
+    picking_filter.set_if_neq(filter);
+    ui.checkbox(&mut pick_normals.enabled, "Show normal of last click");
+
+    ui.separator();
+
+    ui.heading("Auto-save");
+    let mut settings = *auto_save;
+    ui.checkbox(&mut settings.enabled, "Back up unsaved changes");
+    let mut seconds = settings.interval.as_secs();
+    ui.add_enabled(
+      settings.enabled,
+      egui::Slider::new(&mut seconds, 10..=600).text("Interval (seconds)"),
+    );
+    settings.interval = Duration::from_secs(seconds);
+    auto_save.set_if_neq(settings);
+
+    ui.separator();
+
+    ui.heading("Saving");
+    let mut format = *json_format;
+    ui.horizontal(|ui| {
+      for option in JsonFormat::ALL {
+        ui.selectable_value(&mut format, option, option.name());
+      }
+    });
+    json_format.set_if_neq(format);
+  });
+
+  Ok(())
+}
+
+pub fn show_history_ui(
+  mut contexts: EguiContexts,
+  action_history: Res<ActionHistory>,
+  mut messages: MessageWriter<ActionMessage>,
+) -> Result {
+  let ctx = contexts.ctx_mut()?;
+
+  egui::Window::new("History").show(ctx, |ui| {
+    let undo = action_history.undo_label();
+    let redo = action_history.redo_label();
+    ui.label(format!("Undo: {}", undo.as_deref().unwrap_or("nothing")));
+    ui.label(format!("Redo: {}", redo.as_deref().unwrap_or("nothing")));
+    ui.horizontal(|ui| {
+      let undo = egui::Button::new("Undo");
+      if ui.add_enabled(action_history.can_undo(), undo).clicked() {
+        messages.write(ActionMessage::Undo);
+      }
+      let redo = egui::Button::new("Redo");
+      if ui.add_enabled(action_history.can_redo(), redo).clicked() {
+        messages.write(ActionMessage::Redo);
+      }
+    });
+    ui.separator();
+
+    egui::ScrollArea::vertical()
+      .max_height(200.0)
+      .show(ui, |ui| {
+        let mut descriptions = action_history.descriptions().peekable();
+        while let Some((description, applied)) = descriptions.next() {
+          // Mark the most recently applied action, which is what undo reverts.
+          let is_current =
+            applied && descriptions.peek().is_none_or(|(_, applied)| !applied);
+          if is_current {
+            ui.label(egui::RichText::new(format!("> {description}")).strong());
+          } else if applied {
+            ui.label(description);
+          } else {
+            ui.label(egui::RichText::new(description).weak());
+          }
+        }
+
+        if action_history.is_empty() {
+          ui.label("No actions yet.");
+        }
+      });
+  });
+
+  Ok(())
+}
+
+/// Paints the selected blocks with a material and one palette color on every
+/// face.
+pub fn show_paint_ui(
+  mut contexts: EguiContexts,
+  selected: Query<Entity, With<Selected>>,
+  // The material and palette color to paint with.
+  mut paint: Local<(u8, u8)>,
+  mut messages: MessageWriter<ActionMessage>,
+) -> Result {
+  let ctx = contexts.ctx_mut()?;
+
+  egui::Window::new("Paint").show(ctx, |ui| {
+    let (material, color) = &mut *paint;
+    ui.horizontal(|ui| {
+      ui.label("Material");
+      ui.add(egui::DragValue::new(material));
+    });
+    ui.horizontal(|ui| {
+      ui.label("Palette color");
+      ui.add(egui::DragValue::new(color));
+    });
+
+    let button = egui::Button::new("Paint selection");
+    if ui.add_enabled(!selected.is_empty(), button).clicked() {
+      let color = *color;
+      messages.write(ActionMessage::Push(Box::new(PaintMaterialAction::new(
+        selected.iter().collect(),
+        *material,
+        (color, color, color, color, color, color, color),
+      ))));
+    }
+  });
+
+  Ok(())
+}
+
+/// Returns the value every item of `values` shares, or [`None`] if they
+/// differ or there are none.
+fn common<T: PartialEq>(mut values: impl Iterator<Item = T>) -> Option<T> {
+  let first = values.next()?;
+  values.all(|value| value == first).then_some(first)
+}
+
+/// Shows the details of the [`InspectedComponent`], or else of the selected
+/// blocks, with the properties they share when more than one is selected.
+pub fn show_inspector_ui(
+  mut contexts: EguiContexts,
+  inspected: Res<InspectedComponent>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  selection: Query<(&BlockIndex, &BlockData), With<Selected>>,
+) -> Result {
+  let ctx = contexts.ctx_mut()?;
+
+  egui::Window::new("Inspector").show(ctx, |ui| {
+    let component = inspected.0.and_then(|index| {
+      let blueprint = blueprints.get(blueprint.id())?;
+      Some((index, blueprint.data.components.get(index)?))
+    });
+    if let Some((index, component)) = component {
+      ui.heading(format!("Component {index}"));
+      ui.label(format!("Module: {}", component.module));
+      ui.label(format!("Type: {}", component.r#type));
+      ui.label(format!(
+        "Alias: {}",
+        component.alias.as_deref().unwrap_or("none")
+      ));
+
+      ui.separator();
+
+      let mut data = component.data.iter().collect::<Vec<_>>();
+      data.sort_by_key(|(key, _)| *key);
+      for (key, value) in data {
+        let value =
+          serde_json::to_string(value).unwrap_or_else(|_| "?".to_owned());
+        ui.label(format!("{key}: {value}"));
+      }
+      if component.data.is_empty() {
+        ui.label("No data.");
+      }
+      return;
+    }
+
+    let blocks = || selection.iter().map(|(_, block)| &block.0);
+    let mixed = || "mixed".to_owned();
+    match selection.single() {
+      Ok((index, block)) => {
+        ui.heading(format!("Block {}", index.0));
+        let IVec3 { x, y, z } = block.min();
+        ui.label(format!("Position: ({x}, {y}, {z})"));
+      }
+      Err(_) if selection.is_empty() => {
+        ui.label("Select a block or click a component to inspect it.");
+        return;
+      }
+      Err(_) => {
+        ui.heading(format!("{} blocks", selection.iter().count()));
+      }
+    }
+
+    ui.label(format!(
+      "Type: {}",
+      common(blocks().map(|block| block.r#type))
+        .map_or_else(mixed, |type_id| type_id.to_string())
+    ));
+    ui.label(format!(
+      "Material: {}",
+      common(blocks().map(|block| block.material))
+        .map_or_else(mixed, |material| material.to_string())
+    ));
+    ui.label(format!(
+      "Colors: {}",
+      common(blocks().map(|block| block.colors)).map_or_else(
+        mixed,
+        |(a, b, c, d, e, f, g)| format!("{:?}", [a, b, c, d, e, f, g]),
+      )
+    ));
+    ui.label(format!(
+      "Size: {}",
+      common(blocks().map(|block| block.size()))
+        .map_or_else(mixed, |IVec3 { x, y, z }| format!("{x}×{y}×{z}"))
+    ));
+  });
+
+  Ok(())
+}
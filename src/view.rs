@@ -0,0 +1,641 @@
+//! How the blueprint is drawn in the viewport.
+
+use bevy::{
+  asset::RenderAssetUsages,
+  color::ColorToComponents,
+  mesh::{PrimitiveTopology, VertexAttributeValues},
+  pbr::wireframe::{Wireframe, WireframeConfig},
+  platform::collections::{HashMap, HashSet},
+  prelude::*,
+  render::render_resource::Face,
+};
+
+use crate::{
+  BlockData, CommonAssets, FRAME_SIZE, FrameCube, FrameRef, Selected,
+  blueprint::{Block, Blueprint, LoadedBlueprint},
+  no_egui_keyboard_focus,
+  select::SelectionChanged,
+};
+
+#[derive(Default)]
+pub struct ViewPlugin;
+
+impl Plugin for ViewPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<RenderStyle>()
+      .init_resource::<WireframeColor>()
+      .init_resource::<SelectionWireframes>()
+      .init_resource::<FaceColors>()
+      .init_resource::<EmptyFrames>()
+      .init_resource::<FrameCubes>()
+      .init_resource::<BackFaces>()
+      .init_resource::<OuterShell>()
+      .init_resource::<GroundGrid>()
+      .add_systems(Startup, spawn_ground_grid)
+      .add_systems(
+        Update,
+        (
+          (
+            cycle_render_style.run_if(no_egui_keyboard_focus),
+            apply_render_style,
+          )
+            .chain(),
+          apply_wireframe_color,
+          apply_selection_wireframes,
+          apply_face_colors,
+          apply_empty_frames,
+          apply_frame_cubes,
+          apply_back_faces,
+          (
+            toggle_outer_shell.run_if(no_egui_keyboard_focus),
+            apply_outer_shell,
+          )
+            .chain(),
+          apply_ground_grid,
+        ),
+      );
+  }
+}
+
+/// How materials and wireframes are combined when drawing the blueprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub enum RenderStyle {
+  /// Materials lit by the scene.
+  #[default]
+  Shaded,
+  /// Materials without lighting, to read colors.
+  Unlit,
+  /// Only wireframes, to read structure.
+  Wireframe,
+}
+
+impl RenderStyle {
+  /// Every style, in the order they are cycled through.
+  pub const ALL: [Self; 3] = [Self::Shaded, Self::Unlit, Self::Wireframe];
+
+  /// The style after this one, wrapping around after the last.
+  pub fn next(self) -> Self {
+    match self {
+      Self::Shaded => Self::Unlit,
+      Self::Unlit => Self::Wireframe,
+      Self::Wireframe => Self::Shaded,
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Shaded => "Shaded",
+      Self::Unlit => "Unlit",
+      Self::Wireframe => "Wireframe",
+    }
+  }
+}
+
+pub fn cycle_render_style(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut render_style: ResMut<RenderStyle>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::KeyV)
+    && !keycode.pressed(KeyCode::ControlLeft)
+  {
+    *render_style = render_style.next();
+  }
+}
+
+/// Returns the materials added since the last time `events` were read.
+fn added_materials(
+  events: &mut MessageReader<AssetEvent<StandardMaterial>>,
+) -> Vec<AssetId<StandardMaterial>> {
+  events
+    .read()
+    .filter_map(|event| match event {
+      AssetEvent::Added { id } => Some(*id),
+      _ => None,
+    })
+    .collect()
+}
+
+/// Applies the [`RenderStyle`] to the [`WireframeConfig`] and every material
+/// when it changes, and to each material as it is added.
+pub fn apply_render_style(
+  render_style: Res<RenderStyle>,
+  mut events: MessageReader<AssetEvent<StandardMaterial>>,
+  mut wireframe_config: ResMut<WireframeConfig>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+  // The alpha modes of the materials hidden for the wireframe style, to restore
+  // them afterwards.
+  mut alpha_modes: Local<HashMap<AssetId<StandardMaterial>, AlphaMode>>,
+) {
+  let added = added_materials(&mut events);
+  let wireframe = *render_style == RenderStyle::Wireframe;
+  let ids = if render_style.is_changed() {
+    wireframe_config.global = wireframe;
+    materials.ids().collect()
+  } else {
+    added
+  };
+
+  for id in ids {
+    let Some(material) = materials.get_mut(id) else {
+      continue;
+    };
+    material.unlit = *render_style == RenderStyle::Unlit;
+
+    if wireframe {
+      // No fragment's alpha reaches the cutoff, so the material draws nothing
+      // while its wireframe still does.
+      alpha_modes.entry(id).or_insert(material.alpha_mode);
+      material.alpha_mode = AlphaMode::Mask(2.0);
+    } else if let Some(alpha_mode) = alpha_modes.remove(&id) {
+      material.alpha_mode = alpha_mode;
+    }
+  }
+}
+
+/// The color wireframes are drawn in, unless an entity sets its own.
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
+pub struct WireframeColor(pub Color);
+
+impl Default for WireframeColor {
+  fn default() -> Self {
+    Self(Color::WHITE)
+  }
+}
+
+/// Applies the [`WireframeColor`] to the [`WireframeConfig`] when it changes.
+pub fn apply_wireframe_color(
+  wireframe_color: Res<WireframeColor>,
+  mut wireframe_config: ResMut<WireframeConfig>,
+) {
+  if wireframe_color.is_changed() {
+    wireframe_config.default_color = wireframe_color.0;
+  }
+}
+
+/// Settings for drawing wireframes over only the selected blocks, while the
+/// rest are drawn as the [`RenderStyle`] says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct SelectionWireframes {
+  pub enabled: bool,
+}
+
+/// Gives the selected blocks a [`Wireframe`] while [`SelectionWireframes`] is
+/// enabled, and takes it from every other block, whenever either changes.
+pub fn apply_selection_wireframes(
+  mut commands: Commands,
+  selection_wireframes: Res<SelectionWireframes>,
+  mut selection_changed: MessageReader<SelectionChanged>,
+  blocks: Query<(Entity, Has<Selected>, Has<Wireframe>), With<BlockData>>,
+) {
+  // Drain the messages every time, so that old ones don't count next frame.
+  let selection_changed = selection_changed.read().count() > 0;
+  if !(selection_wireframes.is_changed() || selection_changed) {
+    return;
+  }
+
+  for (entity, selected, wireframe) in blocks.iter() {
+    match (selection_wireframes.enabled && selected, wireframe) {
+      (true, false) => {
+        commands.entity(entity).insert(Wireframe);
+      }
+      (false, true) => {
+        commands.entity(entity).remove::<Wireframe>();
+      }
+      _ => {}
+    }
+  }
+}
+
+/// Settings for drawing the back faces of meshes, to see the inside of a hull
+/// from within it.
+///
+/// Back faces are culled by default, since drawing them is slower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct BackFaces {
+  pub visible: bool,
+}
+
+/// Applies [`BackFaces`] to the culling of every material when it changes,
+/// and to each material as it is added.
+pub fn apply_back_faces(
+  back_faces: Res<BackFaces>,
+  mut events: MessageReader<AssetEvent<StandardMaterial>>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+  let added = added_materials(&mut events);
+  let ids = if back_faces.is_changed() {
+    materials.ids().collect()
+  } else {
+    added
+  };
+
+  let cull_mode = (!back_faces.visible).then_some(Face::Back);
+  for id in ids {
+    if let Some(material) = materials.get_mut(id) {
+      material.cull_mode = cull_mode;
+    }
+  }
+}
+
+/// Settings for coloring each face of cube blocks with its own palette color.
+///
+/// The first six entries of [`Block::colors`] are taken to be the faces in
+/// the order +X, -X, +Y, -Y, +Z, -Z, and the seventh is unused. Only cubes are
+/// colored this way, since the faces of other shapes don't map onto the six
+/// directions as directly.
+///
+/// [`Block::colors`]: crate::blueprint::Block::colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct FaceColors {
+  pub enabled: bool,
+}
+
+/// Swaps the mesh of cube blocks for one with per-face vertex colors while
+/// [`FaceColors`] is enabled, and back when it isn't.
+pub fn apply_face_colors(
+  face_colors: Res<FaceColors>,
+  common_assets: Res<CommonAssets>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut blocks: Query<(Ref<BlockData>, &mut Mesh3d)>,
+  // Colored cubes are shared between blocks with the same face colors.
+  mut colored_cubes: Local<HashMap<[[u8; 4]; 6], Handle<Mesh>>>,
+) {
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  for (block, mut mesh) in blocks.iter_mut() {
+    if block.r#type != 0 || !(face_colors.is_changed() || block.is_changed()) {
+      continue;
+    }
+
+    if !face_colors.enabled {
+      mesh.0 = common_assets.block(0);
+      continue;
+    }
+
+    let (pos_x, neg_x, pos_y, neg_y, pos_z, neg_z, _) = block.colors;
+    let faces = [pos_x, neg_x, pos_y, neg_y, pos_z, neg_z].map(|index| {
+      blueprint.data.color(index).map_or([u8::MAX; 4], |color| {
+        [color.r, color.g, color.b, color.opacity]
+      })
+    });
+
+    mesh.0 = colored_cubes
+      .entry(faces)
+      .or_insert_with(|| meshes.add(face_colored_cube(faces)))
+      .clone();
+  }
+}
+
+/// Builds a unit cube whose faces have the sRGBA `faces` as vertex colors, in
+/// the order described by [`FaceColors`].
+fn face_colored_cube(faces: [[u8; 4]; 6]) -> Mesh {
+  let mut mesh = Mesh::from(Cuboid::from_length(1.0));
+
+  let Some(VertexAttributeValues::Float32x3(normals)) =
+    mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+  else {
+    return mesh;
+  };
+
+  let colors = normals
+    .iter()
+    .map(|normal| {
+      let face = match normal {
+        [x, _, _] if *x > 0.5 => 0,
+        [x, _, _] if *x < -0.5 => 1,
+        [_, y, _] if *y > 0.5 => 2,
+        [_, y, _] if *y < -0.5 => 3,
+        [_, _, z] if *z > 0.5 => 4,
+        _ => 5,
+      };
+      let [r, g, b, a] = faces[face];
+      Color::srgba_u8(r, g, b, a).to_linear().to_f32_array()
+    })
+    .collect::<Vec<_>>();
+
+  mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+  mesh
+}
+
+/// Settings for frames that no block is in, which only hold structure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct EmptyFrames {
+  pub hidden: bool,
+}
+
+impl Default for EmptyFrames {
+  fn default() -> Self {
+    Self { hidden: true }
+  }
+}
+
+/// Hides the frames without any blocks in them while [`EmptyFrames`] says so,
+/// updating them as blocks are added, moved and removed.
+///
+/// A block is in the frame whose coordinates it shares.
+pub fn apply_empty_frames(
+  empty_frames: Res<EmptyFrames>,
+  blueprints: Res<Assets<Blueprint>>,
+  blueprint: Res<LoadedBlueprint>,
+  blocks: Query<Ref<BlockData>>,
+  mut removed_blocks: RemovedComponents<BlockData>,
+  mut frames: Query<(Ref<FrameRef>, &mut Visibility)>,
+) {
+  let blocks_changed = removed_blocks.read().count() > 0
+    || blocks.iter().any(|block| block.is_changed());
+  let frames_added = frames.iter().any(|(frame, _)| frame.is_added());
+  if !(empty_frames.is_changed() || blocks_changed || frames_added) {
+    return;
+  }
+
+  let Some(blueprint) = blueprints.get(blueprint.id()) else {
+    return;
+  };
+
+  let occupied = blocks
+    .iter()
+    .map(|block| block.frame())
+    .collect::<HashSet<_>>();
+
+  for (frame, mut visibility) in frames.iter_mut() {
+    let empty = blueprint
+      .data
+      .frame(frame.0)
+      .is_some_and(|frame| !occupied.contains(&frame.coords()));
+    visibility.set_if_neq(if empty && empty_frames.hidden {
+      Visibility::Hidden
+    } else {
+      Visibility::Inherited
+    });
+  }
+}
+
+/// Settings for the cube filling each frame, drawn as a wireframe over its
+/// beams to see the frame grid.
+///
+/// The cubes are shown by default, as frames were drawn and picked before
+/// they had beams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct FrameCubes {
+  pub visible: bool,
+}
+
+impl Default for FrameCubes {
+  fn default() -> Self {
+    Self { visible: true }
+  }
+}
+
+/// Shows or hides the cube of every frame as [`FrameCubes`] says, including
+/// frames spawned since.
+///
+/// Hidden cubes can't be picked, so frames can only be picked while they are
+/// shown.
+pub fn apply_frame_cubes(
+  frame_cubes: Res<FrameCubes>,
+  mut cubes: Query<(Ref<FrameCube>, &mut Visibility)>,
+) {
+  let visibility = if frame_cubes.visible {
+    Visibility::Inherited
+  } else {
+    Visibility::Hidden
+  };
+
+  for (cube, mut cube_visibility) in cubes.iter_mut() {
+    if frame_cubes.is_changed() || cube.is_added() {
+      cube_visibility.set_if_neq(visibility);
+    }
+  }
+}
+
+/// Settings for showing only the blocks on the outside of the blueprint, to
+/// see its silhouette. This only hides blocks, leaving the data as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub struct OuterShell {
+  pub enabled: bool,
+}
+
+pub fn toggle_outer_shell(
+  keycode: Res<ButtonInput<KeyCode>>,
+  mut outer_shell: ResMut<OuterShell>,
+) {
+  // TODO: Make controls configurable.
+  if keycode.just_pressed(KeyCode::KeyO) {
+    outer_shell.enabled = !outer_shell.enabled;
+  }
+}
+
+/// Hides the blocks that no face of can be seen while [`OuterShell`] is
+/// enabled, updating them as blocks are added, moved and removed.
+pub fn apply_outer_shell(
+  outer_shell: Res<OuterShell>,
+  mut removed_blocks: RemovedComponents<BlockData>,
+  mut blocks: Query<(Ref<BlockData>, &mut Visibility)>,
+) {
+  let blocks_changed = removed_blocks.read().count() > 0
+    || blocks.iter().any(|(block, _)| block.is_changed());
+  if !(outer_shell.is_changed() || blocks_changed) {
+    return;
+  }
+
+  if !outer_shell.enabled {
+    for (_, mut visibility) in blocks.iter_mut() {
+      visibility.set_if_neq(Visibility::Inherited);
+    }
+    return;
+  }
+
+  // Only cubes cover the whole face of their neighbours.
+  let mut covered = HashSet::<IVec3>::default();
+  for (block, _) in blocks.iter().filter(|(block, _)| block.r#type == 0) {
+    covered.extend(block_cells(&block));
+  }
+
+  for (block, mut visibility) in blocks.iter_mut() {
+    let exposed = block_cells(&block).any(|cell| {
+      [
+        IVec3::X,
+        IVec3::NEG_X,
+        IVec3::Y,
+        IVec3::NEG_Y,
+        IVec3::Z,
+        IVec3::NEG_Z,
+      ]
+      .into_iter()
+      .map(|direction| cell + direction)
+      .any(|next| !block.contains_cell(next) && !covered.contains(&next))
+    });
+    visibility.set_if_neq(if exposed {
+      Visibility::Inherited
+    } else {
+      Visibility::Hidden
+    });
+  }
+}
+
+/// Iterates over the grid cells a block covers, in blocks.
+fn block_cells(block: &Block) -> impl Iterator<Item = IVec3> + use<> {
+  let (min, size) = (block.min(), block.size());
+  (0..size.x).flat_map(move |x| {
+    (0..size.y)
+      .flat_map(move |y| (0..size.z).map(move |z| min + IVec3::new(x, y, z)))
+  })
+}
+
+/// Settings for the grid on the ground, with a line along every frame
+/// boundary, to judge where blocks are relative to frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Resource)]
+pub struct GroundGrid {
+  pub visible: bool,
+  /// How many frames the grid reaches from the origin along each axis.
+  pub extent: u32,
+  /// Every this many frames, a line is drawn brighter.
+  pub major_every: u32,
+}
+
+impl Default for GroundGrid {
+  fn default() -> Self {
+    Self {
+      visible: true,
+      extent: 16,
+      major_every: 4,
+    }
+  }
+}
+
+/// Marks the entity drawing the [`GroundGrid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct GroundGridMesh;
+
+/// Builds the lines of a [`GroundGrid`] on the XZ plane.
+fn ground_grid_mesh(grid: &GroundGrid) -> Mesh {
+  const MINOR: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
+  const MAJOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+  let extent = grid.extent as i32;
+  let far = extent as f32 * FRAME_SIZE;
+  let (mut positions, mut colors) = (Vec::new(), Vec::new());
+  for i in -extent..=extent {
+    let offset = i as f32 * FRAME_SIZE;
+    let color = if i % grid.major_every.max(1) as i32 == 0 {
+      MAJOR
+    } else {
+      MINOR
+    };
+    positions.extend([
+      [offset, 0.0, -far],
+      [offset, 0.0, far],
+      [-far, 0.0, offset],
+      [far, 0.0, offset],
+    ]);
+    colors.extend([color; 4]);
+  }
+
+  // The lines are lit like everything else, as if they were the ground.
+  let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+  Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+}
+
+pub fn spawn_ground_grid(
+  mut commands: Commands,
+  ground_grid: Res<GroundGrid>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+  commands.spawn((
+    GroundGridMesh,
+    Mesh3d(meshes.add(ground_grid_mesh(&ground_grid))),
+    MeshMaterial3d(materials.add(Color::WHITE)),
+    Transform::default(),
+    Pickable::IGNORE,
+  ));
+}
+
+/// Shows or hides the ground grid as [`GroundGrid`] says, rebuilding its lines
+/// when its extent changes.
+pub fn apply_ground_grid(
+  ground_grid: Res<GroundGrid>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut grid: Query<(&Mesh3d, &mut Visibility), With<GroundGridMesh>>,
+  mut last: Local<Option<GroundGrid>>,
+) {
+  if !ground_grid.is_changed() {
+    return;
+  }
+
+  for (mesh, mut visibility) in grid.iter_mut() {
+    visibility.set_if_neq(if ground_grid.visible {
+      Visibility::Inherited
+    } else {
+      Visibility::Hidden
+    });
+
+    let rebuild = last.is_none_or(|last| {
+      last.extent != ground_grid.extent
+        || last.major_every != ground_grid.major_every
+    });
+    if rebuild && let Some(mesh) = meshes.get_mut(&mesh.0) {
+      *mesh = ground_grid_mesh(&ground_grid);
+    }
+  }
+
+  *last = Some(*ground_grid);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// An app with the materials and the given view settings, but no rendering.
+  fn app(render_style: RenderStyle, back_faces: BackFaces) -> App {
+    let mut app = App::new();
+    app
+      .add_plugins((MinimalPlugins, AssetPlugin::default()))
+      .init_asset::<StandardMaterial>()
+      .init_resource::<WireframeConfig>()
+      .insert_resource(render_style)
+      .insert_resource(back_faces)
+      .add_systems(Update, (apply_render_style, apply_back_faces));
+    app.update();
+    app
+  }
+
+  #[test]
+  fn render_style_applies_to_added_materials() {
+    let mut app = app(RenderStyle::Wireframe, BackFaces::default());
+    let material = app
+      .world_mut()
+      .resource_mut::<Assets<StandardMaterial>>()
+      .add(StandardMaterial::default());
+    app.update();
+    app.update();
+
+    let materials = app.world().resource::<Assets<StandardMaterial>>();
+    let material = materials.get(&material).unwrap();
+    assert!(matches!(material.alpha_mode, AlphaMode::Mask(_)));
+    assert!(app.world().resource::<WireframeConfig>().global);
+  }
+
+  #[test]
+  fn back_faces_apply_to_added_materials() {
+    let mut app = app(RenderStyle::default(), BackFaces { visible: true });
+    let material = app
+      .world_mut()
+      .resource_mut::<Assets<StandardMaterial>>()
+      .add(StandardMaterial::default());
+    app.update();
+    app.update();
+
+    let materials = app.world().resource::<Assets<StandardMaterial>>();
+    assert_eq!(materials.get(&material).unwrap().cull_mode, None);
+  }
+}
@@ -0,0 +1,204 @@
+//! Pushes an interleaved sequence of actions, then undoes and redoes all of
+//! them several times, checking that the blocks, their transforms and the
+//! selection match what they were at each step.
+
+use archean_editor::{
+  BlockData, BlockEntities, BlockIndex, CommonAssets, FRAME_SIZE,
+  FallbackBlockTypes, PickingFilter, Selected,
+  action::{
+    ActionMessage, ActionPlugin, BoxedAction, DeleteBlockAction, FlipAction,
+    InsertBlocksAction, PaintMaterialAction, PlaceAction, SelectionAction,
+    TranslateAction, consume_actions_messages,
+  },
+  blueprint::{Blueprint, FillPattern},
+  shape::GridAxis,
+  spawn_blueprint, sync_blocks,
+};
+use bevy::{ecs::system::RunSystemOnce, prelude::*, state::app::StatesPlugin};
+use bevy_obj::ObjPlugin;
+
+/// A block as it appears in the world: its index, its data, where it is and
+/// whether it is selected. The data is compared through its debug output, as
+/// [`Block`] isn't comparable.
+///
+/// [`Block`]: archean_editor::blueprint::Block
+type Snapshot = Vec<(BlockIndex, String, Transform, bool)>;
+
+/// A step of the sequence, building an action from the current world.
+type Step = fn(&mut World) -> BoxedAction;
+
+/// How many times the whole sequence is undone and redone.
+const ROUNDS: u32 = 3;
+
+#[test]
+fn undo_and_redo_restore_every_step() {
+  let blueprint = Blueprint::synthetic(UVec3::new(4, 2, 2), FillPattern::Solid);
+
+  let mut app = App::new();
+  app
+    .add_plugins((MinimalPlugins, AssetPlugin::default(), StatesPlugin))
+    .init_asset::<Mesh>()
+    .init_asset::<StandardMaterial>()
+    .add_plugins((ObjPlugin, ActionPlugin))
+    .init_resource::<CommonAssets>()
+    .init_resource::<PickingFilter>()
+    .init_resource::<FallbackBlockTypes>()
+    .add_systems(
+      PostUpdate,
+      sync_blocks
+        .after(consume_actions_messages)
+        .before(TransformSystems::Propagate),
+    );
+  app.finish();
+  app.cleanup();
+
+  app
+    .world_mut()
+    .run_system_once(
+      move |mut commands: Commands, common_assets: Res<CommonAssets>| {
+        spawn_blueprint(
+          &mut commands,
+          &common_assets,
+          &PickingFilter::default(),
+          &blueprint,
+        );
+      },
+    )
+    .expect("spawning the blueprint should not fail");
+  app.update();
+
+  let steps: [Step; 17] = [
+    |world| Box::new(SelectionAction(block(world, 0))),
+    |world| Box::new(SelectionAction(block(world, 1))),
+    |world| {
+      Box::new(FlipAction {
+        entities: selection(world),
+        axis: GridAxis::X,
+      })
+    },
+    |world| Box::new(DeleteBlockAction::new([block(world, 1)])),
+    |world| Box::new(SelectionAction(block(world, 2))),
+    |world| {
+      Box::new(FlipAction {
+        entities: selection(world),
+        axis: GridAxis::Y,
+      })
+    },
+    |world| Box::new(DeleteBlockAction::new(selection(world))),
+    |world| {
+      let entity = block(world, 3);
+      let mut placed = world.get::<BlockData>(entity).unwrap().0.clone();
+      placed.pos_y += 4;
+      Box::new(PlaceAction::new(placed))
+    },
+    |world| Box::new(SelectionAction(block(world, 16))),
+    |world| {
+      Box::new(FlipAction {
+        entities: vec![block(world, 16), block(world, 3)],
+        axis: GridAxis::Z,
+      })
+    },
+    |world| {
+      Box::new(DeleteBlockAction::new([block(world, 16), block(world, 4)]))
+    },
+    |world| Box::new(SelectionAction(block(world, 5))),
+    |world| {
+      Box::new(TranslateAction {
+        entities: vec![block(world, 5), block(world, 6)],
+        delta: IVec3::Z * FRAME_SIZE as i32,
+      })
+    },
+    |world| {
+      Box::new(PaintMaterialAction::new(
+        vec![block(world, 6), block(world, 7)],
+        1,
+        (1, 2, 3, 4, 5, 6, 7),
+      ))
+    },
+    |world| {
+      let blocks = selection(world)
+        .into_iter()
+        .map(|entity| world.get::<BlockData>(entity).unwrap().0.clone())
+        .collect();
+      Box::new(InsertBlocksAction::clone_blocks(blocks, IVec3::Y * 8))
+    },
+    |world| {
+      let entity = block(world, 7);
+      let mut pasted = world.get::<BlockData>(entity).unwrap().0.clone();
+      pasted.set_min(IVec3::ZERO);
+      Box::new(InsertBlocksAction::paste(
+        vec![pasted],
+        IVec3::new(0, -8, 0),
+      ))
+    },
+    |world| Box::new(DeleteBlockAction::new(selection(world))),
+  ];
+
+  let mut snapshots = vec![snapshot(app.world_mut())];
+  for step in steps {
+    let action = step(app.world_mut());
+    app.world_mut().write_message(ActionMessage::Push(action));
+    app.update();
+    snapshots.push(snapshot(app.world_mut()));
+  }
+
+  for round in 0..ROUNDS {
+    for position in (0..steps.len()).rev() {
+      app.world_mut().write_message(ActionMessage::Undo);
+      app.update();
+      assert_eq!(
+        snapshot(app.world_mut()),
+        snapshots[position],
+        "undoing to step {position} in round {round} should restore it"
+      );
+    }
+
+    for (position, expected) in snapshots.iter().enumerate().skip(1) {
+      app.world_mut().write_message(ActionMessage::Redo);
+      app.update();
+      assert_eq!(
+        &snapshot(app.world_mut()),
+        expected,
+        "redoing to step {position} in round {round} should restore it"
+      );
+    }
+  }
+
+  // Undoing past the first step and redoing past the last change nothing.
+  app.world_mut().write_message(ActionMessage::Redo);
+  app.update();
+  assert_eq!(snapshot(app.world_mut()), snapshots[steps.len()]);
+  for _ in 0..=steps.len() {
+    app.world_mut().write_message(ActionMessage::Undo);
+    app.update();
+  }
+  assert_eq!(snapshot(app.world_mut()), snapshots[0]);
+}
+
+/// Returns the entity of the block at `index`.
+fn block(world: &mut World, index: usize) -> Entity {
+  world
+    .resource::<BlockEntities>()
+    .entity(index)
+    .unwrap_or_else(|| panic!("block {index} should exist"))
+}
+
+/// Returns the entities of the selected blocks.
+fn selection(world: &mut World) -> Vec<Entity> {
+  world
+    .query_filtered::<Entity, With<Selected>>()
+    .iter(world)
+    .collect()
+}
+
+fn snapshot(world: &mut World) -> Snapshot {
+  let mut blocks = world
+    .query::<(&BlockIndex, &BlockData, &Transform, Has<Selected>)>()
+    .iter(world)
+    .map(|(index, block, transform, selected)| {
+      (*index, format!("{:?}", block.0), *transform, selected)
+    })
+    .collect::<Snapshot>();
+  blocks.sort_by_key(|(index, ..)| *index);
+  blocks
+}